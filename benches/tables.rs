@@ -0,0 +1,281 @@
+//! Micro-benchmarks of every table of the benchmark
+//!
+//! Covers the tables of the main run and the variants only measured in a
+//! scenario: reordering chains, growing tables and the thread-safe tables,
+//! which are timed from a single thread.
+//!
+//! Run with `cargo bench`, or `cargo bench -- <filter>` to only run the
+//! benchmarks whose id contains the filter. Ids have the form
+//! `<table>/<operation>/<load factor>`. Every table is resized to the same
+//! number of bytes like in the main run and filled to each of
+//! BENCH_LOAD_FACTORS, then insertions, successful and failed lookups are
+//! timed through the concrete type of the table. Lookups are timed with
+//! `contains`, so they do not reorder chains.
+//!
+//! Times are written to target/bench/tables.csv. Every run is compared to
+//! the times of the previous run found there, so changes can be compared
+//! offline. HashTables can not remove elements, so removals are not measured
+extern crate hashset_testing;
+extern crate rand;
+
+use hashset_testing::hashset::*;
+use hashset_testing::timing::{Clock, Timer, Timing};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Load factors every table is benchmarked at
+const BENCH_LOAD_FACTORS: [f64; 3] = [0.05, 0.15, 0.3];
+/// Seed of the inserted and missing elements, fixed so runs are comparable
+const SEED: u64 = 0x5eed;
+/// Number of failed lookups timed
+const MISSING_SAMPLES: usize = 1 << 14;
+/// Unmeasured rounds before every timing
+const WARMUP_ROUNDS: usize = 3;
+/// Measured rounds of every timing, outliers are rejected
+const TIMING_SAMPLES: usize = 15;
+/// Relative changes to the previous run below this are reported as noise
+const NOISE_THRESHOLD: f64 = 0.05;
+/// Report of the previous run, replaced by the report of this run
+const REPORT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/bench/tables.csv");
+
+/// Benchmarks every table with `method`, see `Bench::table`
+macro_rules! bench_tables {
+    ($bench:ident.$method:ident, $($table:ty => $name:expr),* $(,)?) => {
+        $($bench.$method::<$table>($name);)*
+    };
+}
+
+/// State of one run of the benchmarks
+struct Bench {
+    filter: Option<String>,
+    timer: Timer,
+    previous: BTreeMap<String, f64>,
+    current: BTreeMap<String, f64>,
+}
+
+impl Bench {
+    /// creates a run filtered by the first argument that is not a flag
+    ///
+    /// `cargo bench` passes `--bench` to every benchmark, so flags are ignored
+    fn new() -> Self {
+        let filter = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+        Self {
+            filter,
+            timer: Timer::new(Clock::Instant, WARMUP_ROUNDS, TIMING_SAMPLES),
+            previous: read_report(Path::new(REPORT)),
+            current: BTreeMap::new(),
+        }
+    }
+
+    /// returns true iff the benchmark `id` is selected by the filter
+    fn selected(&self, id: &str) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| id.contains(filter))
+    }
+
+    /// benchmarks insertions, successful and failed lookups of `H` at every load factor
+    ///
+    /// Tables that can not be resized or filled are reported as failed
+    fn table<H: HashTable<u32> + Default>(&mut self, name: &str) {
+        self.bench(
+            name,
+            |fill| {
+                let mut table = H::default();
+                table.resize_to_bytes(ELEMENT_COUNT << 3, fill)?;
+                Ok(table)
+            },
+            |table, x| table.insert(x),
+            |table, x| table.contains(x),
+        );
+    }
+
+    /// benchmarks a thread-safe table `H` from a single thread, like `table`
+    ///
+    /// Concurrent tables always count collisions, they are counted into
+    /// a counter that is thrown away
+    fn concurrent_table<H: ConcurrentHashTable<u32> + Default>(&mut self, name: &str) {
+        self.bench(
+            name,
+            |fill| {
+                let mut table = H::default();
+                table.resize_to_bytes(ELEMENT_COUNT << 3, fill)?;
+                Ok(table)
+            },
+            |table, x| table.insert(x, &mut 0),
+            |table, x| table.has(x, &mut 0),
+        );
+    }
+
+    /// benchmarks a table at every load factor
+    ///
+    /// `new_table` returns a new table resized for the number of elements
+    /// passed, `insert` and `contains` are the operations timed
+    fn bench<T>(
+        &mut self,
+        name: &str,
+        new_table: impl Fn(usize) -> Result<T, HashTableError>,
+        insert: impl Fn(&mut T, &u32) -> Result<InsertOutcome, HashTableError>,
+        contains: impl Fn(&T, &u32) -> bool,
+    ) {
+        for &load_factor in &BENCH_LOAD_FACTORS {
+            let fill = (load_factor * ELEMENT_COUNT as f64) as usize;
+            let id =
+                |operation: &str| format!("{}/{}/{:.0}%", name, operation, load_factor * 100_f64);
+            if !["insert", "hit", "miss"]
+                .iter()
+                .any(|op| self.selected(&id(op)))
+            {
+                continue;
+            }
+
+            let mut rng = StdRng::seed_from_u64(SEED);
+            let inserted: Vec<u32> = (0..fill).map(|_| rng.gen()).collect();
+            // random numbers nearly always fail
+            let missing: Vec<u32> = (0..MISSING_SAMPLES).map(|_| rng.gen()).collect();
+
+            let filled = new_table(fill).and_then(|mut table| {
+                for x in &inserted {
+                    insert(&mut table, x)?;
+                }
+                Ok(table)
+            });
+            let table = match filled {
+                Ok(table) => table,
+                Err(e) => {
+                    println!("{:<40} failed: {}", id("*"), e);
+                    continue;
+                }
+            };
+
+            if self.selected(&id("insert")) {
+                let timing = self.timer.time(
+                    &inserted,
+                    || new_table(fill).expect("the table was resized before"),
+                    |table, x| insert(table, x).is_ok(),
+                );
+                self.report(id("insert"), timing);
+            }
+            if self.selected(&id("hit")) {
+                let timing = self
+                    .timer
+                    .time(&inserted, || (), |_, x| contains(&table, x));
+                self.report(id("hit"), timing);
+            }
+            if self.selected(&id("miss")) {
+                let timing = self.timer.time(&missing, || (), |_, x| contains(&table, x));
+                self.report(id("miss"), timing);
+            }
+        }
+    }
+
+    /// prints the time of benchmark `id` and its change to the previous run
+    fn report(&mut self, id: String, timing: Timing) {
+        let change = match self.previous.get(&id) {
+            Some(&previous) if previous > 0_f64 => {
+                let change = timing.per_op / previous - 1_f64;
+                if change.abs() < NOISE_THRESHOLD {
+                    format!("{:+.1}% (within noise)", change * 100_f64)
+                } else if change < 0_f64 {
+                    format!("{:+.1}% (improved)", change * 100_f64)
+                } else {
+                    format!("{:+.1}% (regressed)", change * 100_f64)
+                }
+            }
+            _ => "no previous run".to_owned(),
+        };
+        println!(
+            "{:<40} time: {:>8.2} ns  change: {:<22} outliers: {}",
+            id, timing.per_op, change, timing.rejected
+        );
+        self.current.insert(id, timing.per_op);
+    }
+
+    /// writes the report of this run, keeping benchmarks that were filtered out
+    fn finish(mut self) {
+        let mut report = self.previous;
+        report.append(&mut self.current);
+        let path = Path::new(REPORT);
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, write_report(&report)));
+        if let Err(e) = written {
+            println!("could not write {}: {}", REPORT, e);
+        }
+    }
+}
+
+/// reads the times of a report, an empty map if there is none
+fn read_report(path: &Path) -> BTreeMap<String, f64> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let (id, time) = line.rsplit_once(',')?;
+            Some((id.to_owned(), time.parse().ok()?))
+        })
+        .collect()
+}
+
+/// formats the times as csv with a header
+fn write_report(report: &BTreeMap<String, f64>) -> String {
+    let mut csv = "benchmark,time[ns]\n".to_owned();
+    for (id, time) in report {
+        csv.push_str(&format!("{},{}\n", id, time));
+    }
+    csv
+}
+
+fn main() {
+    let mut bench = Bench::new();
+    bench_tables!(bench.table,
+        OpenAddressingTable<u32, QuadraticProber, MulHash> => "Quadratic Mul",
+        OpenAddressingTable<u32, QuadraticProber, ModHash> => "Quadratic Mod",
+        OpenAddressingTable<u32, QuadraticProber, XorShiftHash> => "Quadratic XOR",
+        OpenAddressingTable<u32, LinearProber, MulHash> => "Linear Mul",
+        OpenAddressingTable<u32, LinearProber, ModHash> => "Linear Mod",
+        OpenAddressingTable<u32, LinearProber, XorShiftHash> => "Linear XOR",
+        OpenAddressingTable<u32, TriangularProber, MulHash> => "Triangular Mul",
+        OpenAddressingTable<u32, TriangularProber, ModHash> => "Triangular Mod",
+        OpenAddressingTable<u32, TriangularProber, XorShiftHash> => "Triangular XOR",
+        DirectChainingTable<u32, MulHash> => "Direct Mul",
+        DirectChainingTable<u32, ModHash> => "Direct Mod",
+        DirectChainingTable<u32, XorShiftHash> => "Direct XOR",
+        DirectChainingTable<u32, XorShiftHash, HeadInsertion> => "Direct Head XOR",
+        DirectChainingTable<u32, XorShiftHash, TailInsertion, MoveToFront> => "Direct MTF XOR",
+        DirectChainingTable<u32, XorShiftHash, TailInsertion, Transpose> => "Direct Transpose XOR",
+        SortedChainingTable<u32, MulHash> => "Sorted Mul",
+        SortedChainingTable<u32, ModHash> => "Sorted Mod",
+        SortedChainingTable<u32, XorShiftHash> => "Sorted XOR",
+        SeparateChainingTable<u32, MulHash> => "Separate Mul",
+        SeparateChainingTable<u32, ModHash> => "Separate Mod",
+        SeparateChainingTable<u32, XorShiftHash> => "Separate XOR",
+        SeparateChainingTable<u32, XorShiftHash, HeadInsertion> => "Separate Head XOR",
+        TreeifiedChainingTable<u32, XorShiftHash> => "Treeified XOR",
+        BucketizedChainingTable<u32, XorShiftHash, 2> => "Bucketized 2 XOR",
+        BucketizedChainingTable<u32, XorShiftHash, 4> => "Bucketized 4 XOR",
+        BucketizedChainingTable<u32, XorShiftHash, 8> => "Bucketized 8 XOR",
+        CoalescedTable<u32, MulHash> => "Coalesced Mul",
+        CoalescedTable<u32, ModHash> => "Coalesced Mod",
+        CoalescedTable<u32, XorShiftHash> => "Coalesced XOR",
+        CoalescedTable<u32, XorShiftHash, Eisch> => "Coalesced EISCH XOR",
+        CoalescedTable<u32, XorShiftHash, Lich> => "Coalesced LICH XOR",
+        CoalescedTable<u32, XorShiftHash, Eich> => "Coalesced EICH XOR",
+        LinearHashingTable<u32, XorShiftHash> => "Linear Hashing XOR",
+        RehashingChainingTable<u32, XorShiftHash> => "Rehashing XOR",
+        GrowingOpenAddressingTable<u32, LinearProber, XorShiftHash> => "Incremental Linear XOR",
+        GrowingOpenAddressingTable<u32, LinearProber, XorShiftHash, StopTheWorld> => "Rehashing Linear XOR",
+    );
+    bench_tables!(bench.concurrent_table,
+        StripedChainingTable<u32, XorShiftHash, 1> => "Locked XOR",
+        StripedChainingTable<u32, XorShiftHash> => "Striped XOR",
+        AtomicOpenAddressingTable<LinearProber, XorShiftHash> => "Atomic Linear XOR",
+        AtomicOpenAddressingTable<QuadraticProber, XorShiftHash> => "Atomic Quadratic XOR",
+    );
+    bench.finish();
+}
@@ -0,0 +1,43 @@
+//! Fuzzes every chaining table with its variants of insertion, reordering and bucket size
+#![no_main]
+use hashset_testing::hashset::*;
+use hashset_testing_fuzz::{run, Input};
+use libfuzzer_sys::arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Table {
+    Direct,
+    DirectHead,
+    DirectMoveToFront,
+    DirectTranspose,
+    Sorted,
+    Separate,
+    SeparateHead,
+    Treeified,
+    TreeifiedSmall,
+    Bucketized1,
+    Bucketized4,
+}
+
+fuzz_target!(|input: (Table, Input)| {
+    // ModHash maps the keys to few buckets, so chains get long
+    let (table, input) = input;
+    match table {
+        Table::Direct => run::<DirectChainingTable<u32, ModHash>>(&input),
+        Table::DirectHead => run::<DirectChainingTable<u32, ModHash, HeadInsertion>>(&input),
+        Table::DirectMoveToFront => {
+            run::<DirectChainingTable<u32, ModHash, TailInsertion, MoveToFront>>(&input)
+        }
+        Table::DirectTranspose => {
+            run::<DirectChainingTable<u32, ModHash, HeadInsertion, Transpose>>(&input)
+        }
+        Table::Sorted => run::<SortedChainingTable<u32, ModHash>>(&input),
+        Table::Separate => run::<SeparateChainingTable<u32, ModHash>>(&input),
+        Table::SeparateHead => run::<SeparateChainingTable<u32, ModHash, HeadInsertion>>(&input),
+        Table::Treeified => run::<TreeifiedChainingTable<u32, ModHash>>(&input),
+        Table::TreeifiedSmall => run::<TreeifiedChainingTable<u32, ModHash, 2>>(&input),
+        Table::Bucketized1 => run::<BucketizedChainingTable<u32, ModHash, 1>>(&input),
+        Table::Bucketized4 => run::<BucketizedChainingTable<u32, ModHash, 4>>(&input),
+    }
+});
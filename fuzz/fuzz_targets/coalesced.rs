@@ -0,0 +1,28 @@
+//! Fuzzes CoalescedTable with every coalescing strategy
+#![no_main]
+use hashset_testing::hashset::*;
+use hashset_testing_fuzz::{run, Input};
+use libfuzzer_sys::arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Table {
+    Lisch,
+    Eisch,
+    Lich,
+    Eich,
+    LischXor,
+    EichXor,
+}
+
+fuzz_target!(|input: (Table, Input)| {
+    let (table, input) = input;
+    match table {
+        Table::Lisch => run::<CoalescedTable<u32, ModHash, Lisch>>(&input),
+        Table::Eisch => run::<CoalescedTable<u32, ModHash, Eisch>>(&input),
+        Table::Lich => run::<CoalescedTable<u32, ModHash, Lich>>(&input),
+        Table::Eich => run::<CoalescedTable<u32, ModHash, Eich>>(&input),
+        Table::LischXor => run::<CoalescedTable<u32, XorShiftHash, Lisch>>(&input),
+        Table::EichXor => run::<CoalescedTable<u32, XorShiftHash, Eich>>(&input),
+    }
+});
@@ -0,0 +1,28 @@
+//! Fuzzes the concurrent tables from a single thread
+#![no_main]
+use hashset_testing::hashset::*;
+use hashset_testing_fuzz::{run_concurrent, Input};
+use libfuzzer_sys::arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Table {
+    Striped,
+    StripedSingle,
+    AtomicLinear,
+    AtomicQuadratic,
+}
+
+fuzz_target!(|input: (Table, Input)| {
+    let (table, input) = input;
+    match table {
+        Table::Striped => run_concurrent::<StripedChainingTable<u32, ModHash>>(&input),
+        Table::StripedSingle => run_concurrent::<StripedChainingTable<u32, ModHash, 1>>(&input),
+        Table::AtomicLinear => {
+            run_concurrent::<AtomicOpenAddressingTable<LinearProber, ModHash>>(&input)
+        }
+        Table::AtomicQuadratic => {
+            run_concurrent::<AtomicOpenAddressingTable<QuadraticProber, MulHash>>(&input)
+        }
+    }
+});
@@ -0,0 +1,34 @@
+//! Fuzzes the tables growing while elements are inserted
+#![no_main]
+use hashset_testing::hashset::*;
+use hashset_testing_fuzz::{run, Input};
+use libfuzzer_sys::arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Table {
+    LinearHashing,
+    LinearHashingXor,
+    RehashingChaining,
+    IncrementalLinear,
+    IncrementalQuadratic,
+    StopTheWorldLinear,
+}
+
+fuzz_target!(|input: (Table, Input)| {
+    let (table, input) = input;
+    match table {
+        Table::LinearHashing => run::<LinearHashingTable<u32, ModHash>>(&input),
+        Table::LinearHashingXor => run::<LinearHashingTable<u32, XorShiftHash>>(&input),
+        Table::RehashingChaining => run::<RehashingChainingTable<u32, ModHash>>(&input),
+        Table::IncrementalLinear => {
+            run::<GrowingOpenAddressingTable<u32, LinearProber, ModHash>>(&input)
+        }
+        Table::IncrementalQuadratic => {
+            run::<GrowingOpenAddressingTable<u32, QuadraticProber, MulHash>>(&input)
+        }
+        Table::StopTheWorldLinear => {
+            run::<GrowingOpenAddressingTable<u32, LinearProber, ModHash, StopTheWorld>>(&input)
+        }
+    }
+});
@@ -0,0 +1,38 @@
+//! Fuzzes OpenAddressingTable with every prober and hasher
+#![no_main]
+use hashset_testing::hashset::*;
+use hashset_testing_fuzz::{run, Input};
+use libfuzzer_sys::arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Table {
+    LinearMul,
+    LinearMod,
+    LinearXor,
+    QuadraticMul,
+    QuadraticMod,
+    QuadraticXor,
+    TriangularMul,
+    TriangularMod,
+    TriangularXor,
+}
+
+fuzz_target!(|input: (Table, Input)| {
+    let (table, input) = input;
+    match table {
+        Table::LinearMul => run::<OpenAddressingTable<u32, LinearProber, MulHash>>(&input),
+        Table::LinearMod => run::<OpenAddressingTable<u32, LinearProber, ModHash>>(&input),
+        Table::LinearXor => run::<OpenAddressingTable<u32, LinearProber, XorShiftHash>>(&input),
+        Table::QuadraticMul => run::<OpenAddressingTable<u32, QuadraticProber, MulHash>>(&input),
+        Table::QuadraticMod => run::<OpenAddressingTable<u32, QuadraticProber, ModHash>>(&input),
+        Table::QuadraticXor => {
+            run::<OpenAddressingTable<u32, QuadraticProber, XorShiftHash>>(&input)
+        }
+        Table::TriangularMul => run::<OpenAddressingTable<u32, TriangularProber, MulHash>>(&input),
+        Table::TriangularMod => run::<OpenAddressingTable<u32, TriangularProber, ModHash>>(&input),
+        Table::TriangularXor => {
+            run::<OpenAddressingTable<u32, TriangularProber, XorShiftHash>>(&input)
+        }
+    }
+});
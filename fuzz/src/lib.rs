@@ -0,0 +1,134 @@
+//! Shared driver of the fuzz targets
+//!
+//! Every target decodes a table type and an `Input` from the fuzzer,
+//! runs the operations of the input on a new table of that type and
+//! compares every result with a `HashSet`. The invariants of the table
+//! are checked after every operation, so a broken table is reported at
+//! the operation that broke it. HashTables can not remove elements and
+//! resizing clears some of them, so tables are only resized before the
+//! first operation, to an arbitrary size
+extern crate arbitrary;
+extern crate hashset_testing;
+
+use arbitrary::Arbitrary;
+use hashset_testing::hashset::*;
+use std::collections::HashSet;
+
+/// Largest budget of bytes a table is resized to
+const MAX_BYTES: u32 = (ELEMENT_COUNT as u32) << 4;
+
+/// Operations on one table, with its size
+#[derive(Arbitrary, Debug)]
+pub struct Input {
+    /// bytes and elements the table is resized to, if any
+    pub size: Option<(u32, u16)>,
+    pub operations: Vec<Operation>,
+}
+
+/// One operation on a table
+#[derive(Arbitrary, Debug)]
+pub enum Operation {
+    Insert(Key),
+    Has(Key),
+}
+
+/// Pattern of a key, so weak hashers see the keys that are hard for them
+#[derive(Arbitrary, Clone, Copy, Debug)]
+pub enum Key {
+    /// any key
+    Raw(u32),
+    /// one of few keys, so hits and duplicates are common
+    Small(u8),
+    /// a multiple of ELEMENT_COUNT, all hashed to one bucket by ModHash
+    Multiple(u16),
+    /// a small key shifted left, leaving the low bits empty
+    Shifted(u8, u8),
+}
+
+impl Key {
+    /// the value of the key
+    pub fn value(self) -> u32 {
+        match self {
+            Key::Raw(x) => x,
+            Key::Small(x) => x as u32,
+            Key::Multiple(x) => (x as u32).wrapping_mul(ELEMENT_COUNT as u32),
+            Key::Shifted(x, shift) => (x as u32) << (shift % 32),
+        }
+    }
+}
+
+/// runs `input` on a new table of type `H` and compares it with a HashSet
+///
+/// Panics if a result differs from the model or an invariant is broken.
+/// Insertions may fail with `Full` or `ProbeCycle`, but may not change
+/// the elements of the table then
+pub fn run<H: HashTable<u32> + Default>(input: &Input) {
+    let mut table = Box::new(H::default());
+    if let Some((bytes, elements)) = input.size {
+        // tables that can not be sized like this keep their default size
+        let _ = table.resize_to_bytes((bytes % MAX_BYTES) as usize, elements as usize);
+    }
+    let mut model = HashSet::new();
+    for operation in &input.operations {
+        match *operation {
+            Operation::Insert(key) => {
+                let val = key.value();
+                match table.insert(&val) {
+                    Ok(InsertOutcome::Inserted) => assert!(model.insert(val)),
+                    Ok(InsertOutcome::AlreadyPresent) => assert!(model.contains(&val)),
+                    Err(HashTableError::Full) | Err(HashTableError::ProbeCycle) => {
+                        assert!(!model.contains(&val))
+                    }
+                    Err(e) => panic!("inserting {} failed: {}", val, e),
+                }
+            }
+            Operation::Has(key) => {
+                let val = key.value();
+                let stats = table.probe_stats(&val);
+                assert_eq!(table.has(&val), model.contains(&val));
+                assert_eq!(stats.found, model.contains(&val));
+            }
+        }
+        if let Err(e) = table.check_invariants() {
+            panic!("{:?} broke the table: {}", operation, e);
+        }
+    }
+    for val in &model {
+        assert!(table.contains(val), "lost {}", val);
+    }
+}
+
+/// runs `input` on a new concurrent table of type `H` from a single thread
+///
+/// The table is resized before it is used, like in `run`
+pub fn run_concurrent<H: ConcurrentHashTable<u32> + Default>(input: &Input) {
+    let mut table = H::default();
+    if let Some((bytes, elements)) = input.size {
+        // tables that can not be sized like this keep their default size
+        let _ = table.resize_to_bytes((bytes % MAX_BYTES) as usize, elements as usize);
+    }
+    let mut model = HashSet::new();
+    let mut collisions = 0;
+    for operation in &input.operations {
+        match *operation {
+            Operation::Insert(key) => {
+                let val = key.value();
+                match table.insert(&val, &mut collisions) {
+                    Ok(InsertOutcome::Inserted) => assert!(model.insert(val)),
+                    Ok(InsertOutcome::AlreadyPresent) => assert!(model.contains(&val)),
+                    Err(HashTableError::Full) | Err(HashTableError::ProbeCycle) => {
+                        assert!(!model.contains(&val))
+                    }
+                    Err(e) => panic!("inserting {} failed: {}", val, e),
+                }
+            }
+            Operation::Has(key) => {
+                let val = key.value();
+                assert_eq!(table.has(&val, &mut collisions), model.contains(&val));
+            }
+        }
+        if let Err(e) = table.check_invariants() {
+            panic!("{:?} broke the table: {}", operation, e);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Module for measuring heap usage
+//!
+//! Contains a global allocator counting the bytes currently allocated.
+//! It is only installed if the feature `count-allocations` is enabled,
+//! otherwise `allocated_bytes` always returns None.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of bytes currently allocated through CountingAllocator
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Global allocator counting allocated bytes
+///
+/// Forwards every call to the system allocator and keeps track of the
+/// number of bytes requested. Overhead of the system allocator itself
+/// is not counted.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+/// returns the number of bytes currently allocated on the heap
+///
+/// returns None if the CountingAllocator is not installed
+pub fn allocated_bytes() -> Option<usize> {
+    if cfg!(feature = "count-allocations") {
+        Some(ALLOCATED.load(Ordering::Relaxed))
+    } else {
+        None
+    }
+}
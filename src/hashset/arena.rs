@@ -0,0 +1,504 @@
+use super::{HashTableError, InsertOutcome, InvariantViolation};
+use std::mem::size_of;
+
+/// Index marking the end of a chain
+pub const NIL: u32 = u32::MAX;
+
+/// Node of a chain stored in a NodeArena
+///
+/// Points to the next node of the chain by its index in the arena
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Node<T> {
+    /// element stored in the node
+    pub value: T,
+    /// index of the next node of the chain, NIL at the end of the chain
+    pub next: u32,
+}
+
+/// Where new elements are linked into a chain
+pub trait InsertionPoint {
+    /// Link new elements before the first node instead of after the last node
+    const AT_HEAD: bool;
+}
+
+/// New elements are appended after the last node of the chain
+pub struct TailInsertion;
+impl InsertionPoint for TailInsertion {
+    const AT_HEAD: bool = false;
+}
+
+/// New elements are linked before the first node of the chain
+///
+/// Elements inserted last are found first
+pub struct HeadInsertion;
+impl InsertionPoint for HeadInsertion {
+    const AT_HEAD: bool = true;
+}
+
+/// How a chain is reordered after a successful lookup
+///
+/// Self-organizing chains move frequently searched elements to the
+/// front, so they are found with fewer collisions
+pub trait ChainOrganization {
+    /// reorders the chain starting at `head` after the node at `index` was found
+    ///
+    /// `previous` is the node before it, NIL if it is the first node
+    fn found<T: PartialEq + Copy>(
+        nodes: &mut NodeArena<T>,
+        head: &mut u32,
+        previous: u32,
+        index: u32,
+    );
+}
+
+/// The chain is never reordered
+pub struct Static;
+impl ChainOrganization for Static {
+    fn found<T: PartialEq + Copy>(_: &mut NodeArena<T>, _: &mut u32, _: u32, _: u32) {}
+}
+
+/// The node found is moved to the front of the chain
+pub struct MoveToFront;
+impl ChainOrganization for MoveToFront {
+    fn found<T: PartialEq + Copy>(
+        nodes: &mut NodeArena<T>,
+        head: &mut u32,
+        previous: u32,
+        index: u32,
+    ) {
+        if previous == NIL {
+            return;
+        }
+        nodes.get_mut(previous).next = nodes.get(index).next;
+        nodes.get_mut(index).next = *head;
+        *head = index;
+    }
+}
+
+/// The node found is swapped with the node before it
+///
+/// Only the values are swapped, the links stay the same
+pub struct Transpose;
+impl ChainOrganization for Transpose {
+    fn found<T: PartialEq + Copy>(
+        nodes: &mut NodeArena<T>,
+        _: &mut u32,
+        previous: u32,
+        index: u32,
+    ) {
+        if previous == NIL {
+            return;
+        }
+        let value = nodes.get(index).value;
+        nodes.get_mut(index).value = nodes.get(previous).value;
+        nodes.get_mut(previous).value = value;
+    }
+}
+
+/// Contiguous storage for the nodes of every chain of a table
+///
+/// A chain is identified by the index of its first node, NIL for an
+/// empty chain. Nodes are never moved, so indices stay valid. Released
+/// chains are kept in a free list and their nodes reused by later pushes.
+/// All chains are traversed iteratively.
+pub struct NodeArena<T> {
+    nodes: Vec<Node<T>>,
+    /// first node of the chain of released nodes
+    free: u32,
+}
+
+impl<T: PartialEq + Copy> NodeArena<T> {
+    /// creates an arena with room for `capacity` nodes
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut nodes = Vec::new();
+        nodes.reserve_exact(capacity);
+        Self { nodes, free: NIL }
+    }
+
+    /// returns the node at `index`
+    pub fn get(&self, index: u32) -> &Node<T> {
+        &self.nodes[index as usize]
+    }
+
+    /// returns the node at `index` mutably
+    pub fn get_mut(&mut self, index: u32) -> &mut Node<T> {
+        &mut self.nodes[index as usize]
+    }
+
+    /// stores a new node and returns its index
+    ///
+    /// Reuses a released node if there is one. Otherwise grows by a
+    /// sixteenth of its size if it is full, so a table sized for slightly
+    /// fewer nodes does not double its memory.
+    /// Fails with `HashTableError::Full` if no more nodes can be indexed
+    pub fn push(&mut self, value: T, next: u32) -> Result<u32, HashTableError> {
+        if self.free != NIL {
+            let index = self.free;
+            self.free = self.get(index).next;
+            *self.get_mut(index) = Node { value, next };
+            return Ok(index);
+        }
+        if self.nodes.len() >= NIL as usize {
+            return Err(HashTableError::Full);
+        }
+        if self.nodes.len() == self.nodes.capacity() {
+            self.nodes.reserve_exact(self.nodes.len() / 16 + 1);
+        }
+        self.nodes.push(Node { value, next });
+        Ok((self.nodes.len() - 1) as u32)
+    }
+
+    /// checks if the chain starting at `head` contains an element
+    pub fn contains(&self, head: u32, searched: &T) -> bool {
+        let mut index = head;
+        while index != NIL {
+            let node = self.get(index);
+            if node.value == *searched {
+                return true;
+            }
+            index = node.next;
+        }
+        false
+    }
+
+    /// checks if the chain starting at `head` contains an element and counts collisions
+    ///
+    /// Every node not containing the element that was checked is added to `collisions`
+    pub fn contains_counting(&self, head: u32, searched: &T, collisions: &mut usize) -> bool {
+        let mut index = head;
+        while index != NIL {
+            let node = self.get(index);
+            if node.value == *searched {
+                return true;
+            }
+            *collisions += 1;
+            index = node.next;
+        }
+        false
+    }
+
+    /// checks if the chain starting at `head` contains an element and reorders it
+    ///
+    /// Every node not containing the element that was checked is added to
+    /// `collisions`. If the element is found the chain is reordered by `O`
+    pub fn find<O: ChainOrganization>(
+        &mut self,
+        head: &mut u32,
+        searched: &T,
+        collisions: &mut usize,
+    ) -> bool {
+        let mut previous = NIL;
+        let mut index = *head;
+        while index != NIL {
+            let node = self.get(index);
+            if node.value == *searched {
+                O::found(self, head, previous, index);
+                return true;
+            }
+            *collisions += 1;
+            previous = index;
+            index = node.next;
+        }
+        false
+    }
+
+    /// inserts an element into the chain starting at `head`
+    ///
+    /// The whole chain is searched for the element first, every node
+    /// checked before the element is found or linked is added to `collisions`.
+    /// `head` is updated if the element becomes the first node of the chain
+    pub fn insert<I: InsertionPoint>(
+        &mut self,
+        head: &mut u32,
+        val: &T,
+        collisions: &mut usize,
+    ) -> Result<InsertOutcome, HashTableError> {
+        let mut last = NIL;
+        let mut index = *head;
+        while index != NIL {
+            let node = self.get(index);
+            if node.value == *val {
+                return Ok(InsertOutcome::AlreadyPresent);
+            }
+            *collisions += 1;
+            last = index;
+            index = node.next;
+        }
+        if I::AT_HEAD || last == NIL {
+            *head = self.push(*val, *head)?;
+        } else {
+            let new = self.push(*val, NIL)?;
+            self.get_mut(last).next = new;
+        }
+        Ok(InsertOutcome::Inserted)
+    }
+
+    /// returns the number of bytes the arena allocated on the heap
+    pub fn heap_size(&self) -> usize {
+        self.nodes.capacity() * size_of::<Node<T>>()
+    }
+
+    /// releases every node of the chain starting at `head`, to be reused by `push`
+    pub fn release(&mut self, head: u32) {
+        if head == NIL {
+            return;
+        }
+        let mut last = head;
+        while self.get(last).next != NIL {
+            last = self.get(last).next;
+        }
+        self.get_mut(last).next = self.free;
+        self.free = head;
+    }
+
+    /// returns the first released node, NIL if there is none
+    ///
+    /// Released nodes form a chain like any other
+    pub fn free_list(&self) -> u32 {
+        self.free
+    }
+
+    /// returns the number of nodes stored, including released nodes
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// returns the values of the chain starting at `head` in order
+    ///
+    /// Marks every node of the chain in `visited`, which holds a flag for
+    /// every node of the arena. Fails if the chain links out of the arena
+    /// or reaches a node marked before, by a cycle or by sharing it with
+    /// another chain
+    pub fn chain_values(
+        &self,
+        head: u32,
+        visited: &mut [bool],
+    ) -> Result<Vec<T>, InvariantViolation> {
+        let mut values = Vec::new();
+        let mut index = head;
+        while index != NIL {
+            let node = self
+                .nodes
+                .get(index as usize)
+                .ok_or(InvariantViolation::OutOfBounds {
+                    index: index as usize,
+                })?;
+            if visited[index as usize] {
+                return Err(InvariantViolation::Cycle {
+                    index: index as usize,
+                });
+            }
+            visited[index as usize] = true;
+            values.push(node.value);
+            index = node.next;
+        }
+        Ok(values)
+    }
+}
+
+impl<T: Ord + Copy> NodeArena<T> {
+    /// checks if the sorted chain starting at `head` contains an element
+    ///
+    /// The chain has to be sorted in ascending order. The search stops at the
+    /// first node not smaller than the element
+    pub fn contains_sorted(&self, head: u32, searched: &T) -> bool {
+        let mut index = head;
+        while index != NIL {
+            let node = self.get(index);
+            if node.value >= *searched {
+                return node.value == *searched;
+            }
+            index = node.next;
+        }
+        false
+    }
+
+    /// checks if the sorted chain starting at `head` contains an element and counts collisions
+    ///
+    /// Searches like `contains_sorted`. Every node not containing the
+    /// element that was checked is added to `collisions`
+    pub fn contains_sorted_counting(
+        &self,
+        head: u32,
+        searched: &T,
+        collisions: &mut usize,
+    ) -> bool {
+        let mut index = head;
+        while index != NIL {
+            let node = self.get(index);
+            if node.value == *searched {
+                return true;
+            }
+            *collisions += 1;
+            if node.value > *searched {
+                return false;
+            }
+            index = node.next;
+        }
+        false
+    }
+
+    /// inserts an element into the sorted chain starting at `head`
+    ///
+    /// The element is linked before the first node greater than it, so the
+    /// chain stays sorted in ascending order. Every node checked before the
+    /// element is found or linked is added to `collisions`.
+    /// `head` is updated if the element becomes the first node of the chain
+    pub fn insert_sorted(
+        &mut self,
+        head: &mut u32,
+        val: &T,
+        collisions: &mut usize,
+    ) -> Result<InsertOutcome, HashTableError> {
+        let mut previous = NIL;
+        let mut index = *head;
+        while index != NIL {
+            let node = self.get(index);
+            if node.value == *val {
+                return Ok(InsertOutcome::AlreadyPresent);
+            }
+            if node.value > *val {
+                break;
+            }
+            *collisions += 1;
+            previous = index;
+            index = node.next;
+        }
+        let new = self.push(*val, index)?;
+        if previous == NIL {
+            *head = new;
+        } else {
+            self.get_mut(previous).next = new;
+        }
+        Ok(InsertOutcome::Inserted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// values of the chain starting at `head`, in order
+    fn chain<T: PartialEq + Copy>(nodes: &NodeArena<T>, head: u32) -> Vec<T> {
+        let mut values = Vec::new();
+        let mut index = head;
+        while index != NIL {
+            values.push(nodes.get(index).value);
+            index = nodes.get(index).next;
+        }
+        values
+    }
+
+    /// arena with one chain holding 1, 2, 3, 4
+    fn filled() -> (NodeArena<u32>, u32) {
+        let mut nodes = NodeArena::with_capacity(4);
+        let mut head = NIL;
+        let mut collisions = 0;
+        for x in 1..=4 {
+            nodes
+                .insert::<TailInsertion>(&mut head, &x, &mut collisions)
+                .unwrap();
+        }
+        // the ith element was appended after i - 1 collisions
+        assert_eq!(collisions, 1 + 2 + 3);
+        (nodes, head)
+    }
+
+    #[test]
+    fn insertion_points() {
+        let (nodes, head) = filled();
+        assert_eq!(chain(&nodes, head), vec![1, 2, 3, 4]);
+
+        let mut nodes = NodeArena::with_capacity(0);
+        let mut head = NIL;
+        let mut collisions = 0;
+        for x in 1..=4 {
+            nodes
+                .insert::<HeadInsertion>(&mut head, &x, &mut collisions)
+                .unwrap();
+        }
+        assert_eq!(chain(&nodes, head), vec![4, 3, 2, 1]);
+        assert_eq!(
+            nodes.insert::<HeadInsertion>(&mut head, &2, &mut collisions),
+            Ok(InsertOutcome::AlreadyPresent)
+        );
+    }
+
+    #[test]
+    fn counts_collisions() {
+        let (nodes, head) = filled();
+        let mut collisions = 0;
+        assert!(nodes.contains_counting(head, &3, &mut collisions));
+        assert_eq!(collisions, 2);
+        assert!(!nodes.contains_counting(head, &5, &mut collisions));
+        assert_eq!(collisions, 2 + 4);
+        assert!(nodes.contains(head, &3));
+        assert!(!nodes.contains(head, &5));
+    }
+
+    #[test]
+    fn chain_organizations() {
+        let (mut nodes, mut head) = filled();
+        let mut collisions = 0;
+        assert!(nodes.find::<Static>(&mut head, &3, &mut collisions));
+        assert_eq!(chain(&nodes, head), vec![1, 2, 3, 4]);
+
+        assert!(nodes.find::<Transpose>(&mut head, &3, &mut collisions));
+        assert_eq!(chain(&nodes, head), vec![1, 3, 2, 4]);
+
+        assert!(nodes.find::<MoveToFront>(&mut head, &4, &mut collisions));
+        assert_eq!(chain(&nodes, head), vec![4, 1, 3, 2]);
+
+        // finding the first element does not change the chain
+        assert!(nodes.find::<MoveToFront>(&mut head, &4, &mut collisions));
+        assert!(nodes.find::<Transpose>(&mut head, &4, &mut collisions));
+        assert_eq!(chain(&nodes, head), vec![4, 1, 3, 2]);
+        assert!(!nodes.find::<MoveToFront>(&mut head, &5, &mut collisions));
+        assert_eq!(chain(&nodes, head), vec![4, 1, 3, 2]);
+    }
+
+    #[test]
+    fn sorted_chains() {
+        let mut nodes = NodeArena::with_capacity(0);
+        let mut head = NIL;
+        let mut collisions = 0;
+        for x in &[5, 1, 4, 2, 3, 4] {
+            nodes.insert_sorted(&mut head, x, &mut collisions).unwrap();
+        }
+        assert_eq!(chain(&nodes, head), vec![1, 2, 3, 4, 5]);
+
+        let mut collisions = 0;
+        assert!(!nodes.contains_sorted_counting(head, &0, &mut collisions));
+        assert_eq!(collisions, 1);
+        assert!(nodes.contains_sorted_counting(head, &3, &mut collisions));
+        assert_eq!(collisions, 1 + 2);
+        assert!(!nodes.contains_sorted_counting(head, &6, &mut collisions));
+        assert_eq!(collisions, 1 + 2 + 5);
+        for x in 0..=6 {
+            assert_eq!(nodes.contains_sorted(head, &x), (1..=5).contains(&x));
+        }
+    }
+
+    #[test]
+    fn released_nodes_are_reused() {
+        let (mut nodes, head) = filled();
+        nodes.release(head);
+        assert_eq!(chain(&nodes, nodes.free_list()), vec![1, 2, 3, 4]);
+        let mut head = NIL;
+        let mut collisions = 0;
+        for x in 5..=9 {
+            nodes
+                .insert::<TailInsertion>(&mut head, &x, &mut collisions)
+                .unwrap();
+        }
+        assert_eq!(chain(&nodes, head), vec![5, 6, 7, 8, 9]);
+        assert_eq!(nodes.free_list(), NIL);
+        assert_eq!(nodes.node_count(), 5);
+    }
+
+    #[test]
+    fn heap_size_counts_capacity() {
+        let nodes = NodeArena::<u32>::with_capacity(10);
+        assert_eq!(nodes.heap_size(), 10 * size_of::<Node<u32>>());
+    }
+}
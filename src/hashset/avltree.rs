@@ -0,0 +1,327 @@
+use super::{HashTableError, InsertOutcome, InvariantViolation, NIL};
+use std::mem::size_of;
+
+/// Node of an AVL tree stored in a TreeArena
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeNode<T> {
+    /// element stored in the node
+    pub value: T,
+    /// index of the left child, NIL if there is none
+    pub left: u32,
+    /// index of the right child, NIL if there is none
+    pub right: u32,
+    /// height of the subtree rooted at this node, a leaf has height 1
+    pub height: u32,
+}
+
+/// Contiguous storage for the nodes of several AVL trees
+///
+/// A tree is identified by the index of its root, NIL for an empty tree.
+/// Nodes are never removed, so indices stay valid. The heights of the
+/// two subtrees of every node differ by at most one, so every tree
+/// with n nodes has a height of at most about 1.44 log2(n).
+pub struct TreeArena<T> {
+    nodes: Vec<TreeNode<T>>,
+}
+
+impl<T: Ord + Copy> Default for TreeArena<T> {
+    fn default() -> Self {
+        Self { nodes: Vec::new() }
+    }
+}
+
+impl<T: Ord + Copy> TreeArena<T> {
+    /// returns the height of the subtree rooted at `index`
+    fn height(&self, index: u32) -> u32 {
+        if index == NIL {
+            0
+        } else {
+            self.nodes[index as usize].height
+        }
+    }
+
+    /// recalculates the height of the node at `index` from its children
+    fn update_height(&mut self, index: u32) {
+        let node = self.nodes[index as usize];
+        let height = 1 + u32::max(self.height(node.left), self.height(node.right));
+        self.nodes[index as usize].height = height;
+    }
+
+    /// rotates the subtree rooted at `index` to the right and returns the new root
+    fn rotate_right(&mut self, index: u32) -> u32 {
+        let left = self.nodes[index as usize].left;
+        self.nodes[index as usize].left = self.nodes[left as usize].right;
+        self.nodes[left as usize].right = index;
+        self.update_height(index);
+        self.update_height(left);
+        left
+    }
+
+    /// rotates the subtree rooted at `index` to the left and returns the new root
+    fn rotate_left(&mut self, index: u32) -> u32 {
+        let right = self.nodes[index as usize].right;
+        self.nodes[index as usize].right = self.nodes[right as usize].left;
+        self.nodes[right as usize].left = index;
+        self.update_height(index);
+        self.update_height(right);
+        right
+    }
+
+    /// restores the AVL property at `index` and returns the new root of the subtree
+    ///
+    /// The subtrees of the node have to be balanced already
+    fn rebalance(&mut self, index: u32) -> u32 {
+        self.update_height(index);
+        let node = self.nodes[index as usize];
+        let (left, right) = (self.height(node.left), self.height(node.right));
+        if left > right + 1 {
+            let child = self.nodes[node.left as usize];
+            if self.height(child.left) < self.height(child.right) {
+                self.nodes[index as usize].left = self.rotate_left(node.left);
+            }
+            return self.rotate_right(index);
+        }
+        if right > left + 1 {
+            let child = self.nodes[node.right as usize];
+            if self.height(child.right) < self.height(child.left) {
+                self.nodes[index as usize].right = self.rotate_right(node.right);
+            }
+            return self.rotate_left(index);
+        }
+        index
+    }
+
+    /// checks if the tree rooted at `root` contains an element
+    pub fn contains(&self, root: u32, searched: &T) -> bool {
+        let mut index = root;
+        while index != NIL {
+            let node = &self.nodes[index as usize];
+            if node.value == *searched {
+                return true;
+            }
+            index = if *searched < node.value {
+                node.left
+            } else {
+                node.right
+            };
+        }
+        false
+    }
+
+    /// checks if the tree rooted at `root` contains an element and counts collisions
+    ///
+    /// Every node not containing the element that was compared with it
+    /// is added to `collisions`
+    pub fn contains_counting(&self, root: u32, searched: &T, collisions: &mut usize) -> bool {
+        let mut index = root;
+        while index != NIL {
+            let node = &self.nodes[index as usize];
+            if node.value == *searched {
+                return true;
+            }
+            *collisions += 1;
+            index = if *searched < node.value {
+                node.left
+            } else {
+                node.right
+            };
+        }
+        false
+    }
+
+    /// inserts an element into the tree rooted at `root`
+    ///
+    /// Every node compared with the element before it is found or inserted
+    /// is added to `collisions`. `root` is updated if the tree was rebalanced.
+    /// Fails with `HashTableError::Full` if no more nodes can be indexed
+    pub fn insert(
+        &mut self,
+        root: &mut u32,
+        val: &T,
+        collisions: &mut usize,
+    ) -> Result<InsertOutcome, HashTableError> {
+        let (new_root, outcome) = self.insert_at(*root, val, collisions)?;
+        *root = new_root;
+        Ok(outcome)
+    }
+
+    /// inserts an element into the subtree rooted at `index`
+    ///
+    /// returns the new root of the subtree. Recursion is bounded by the
+    /// height of the tree
+    fn insert_at(
+        &mut self,
+        index: u32,
+        val: &T,
+        collisions: &mut usize,
+    ) -> Result<(u32, InsertOutcome), HashTableError> {
+        if index == NIL {
+            if self.nodes.len() >= NIL as usize {
+                return Err(HashTableError::Full);
+            }
+            self.nodes.push(TreeNode {
+                value: *val,
+                left: NIL,
+                right: NIL,
+                height: 1,
+            });
+            return Ok(((self.nodes.len() - 1) as u32, InsertOutcome::Inserted));
+        }
+        let node = self.nodes[index as usize];
+        if node.value == *val {
+            return Ok((index, InsertOutcome::AlreadyPresent));
+        }
+        *collisions += 1;
+        let outcome = if *val < node.value {
+            let (left, outcome) = self.insert_at(node.left, val, collisions)?;
+            self.nodes[index as usize].left = left;
+            outcome
+        } else {
+            let (right, outcome) = self.insert_at(node.right, val, collisions)?;
+            self.nodes[index as usize].right = right;
+            outcome
+        };
+        Ok((self.rebalance(index), outcome))
+    }
+
+    /// returns the number of bytes the arena allocated on the heap
+    pub fn heap_size(&self) -> usize {
+        self.nodes.capacity() * size_of::<TreeNode<T>>()
+    }
+
+    /// returns the number of nodes stored
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// returns true iff `nodes` more nodes can be indexed
+    pub fn has_room(&self, nodes: usize) -> bool {
+        self.nodes.len() + nodes <= NIL as usize
+    }
+
+    /// returns the values of the tree rooted at `root` in ascending order
+    ///
+    /// Marks every node of the tree in `visited` like
+    /// `NodeArena::chain_values`. Fails with a violation of `bucket` if
+    /// the tree is not a search tree, a height is wrong or a node is unbalanced
+    pub fn tree_values(
+        &self,
+        bucket: usize,
+        root: u32,
+        visited: &mut [bool],
+    ) -> Result<Vec<T>, InvariantViolation> {
+        let mut values = Vec::new();
+        self.collect(bucket, root, visited, &mut values)?;
+        if values.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(InvariantViolation::Order { bucket });
+        }
+        Ok(values)
+    }
+
+    /// appends the values of the subtree at `index` in order and returns its height
+    ///
+    /// Recursion is bounded by the height of the tree, as every node is visited once
+    fn collect(
+        &self,
+        bucket: usize,
+        index: u32,
+        visited: &mut [bool],
+        values: &mut Vec<T>,
+    ) -> Result<u32, InvariantViolation> {
+        if index == NIL {
+            return Ok(0);
+        }
+        let node = *self
+            .nodes
+            .get(index as usize)
+            .ok_or(InvariantViolation::OutOfBounds {
+                index: index as usize,
+            })?;
+        if visited[index as usize] {
+            return Err(InvariantViolation::Cycle {
+                index: index as usize,
+            });
+        }
+        visited[index as usize] = true;
+        let left = self.collect(bucket, node.left, visited, values)?;
+        values.push(node.value);
+        let right = self.collect(bucket, node.right, visited, values)?;
+        if left.abs_diff(right) > 1 || node.height != 1 + left.max(right) {
+            return Err(InvariantViolation::Order { bucket });
+        }
+        Ok(node.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// checks the tree rooted at `index` and returns its height
+    ///
+    /// Every value has to be in (low, high), the heights have to be
+    /// correct and the subtrees of every node balanced
+    fn check(arena: &TreeArena<u32>, index: u32, low: Option<u32>, high: Option<u32>) -> u32 {
+        if index == NIL {
+            return 0;
+        }
+        let node = arena.nodes[index as usize];
+        assert!(low.is_none_or(|low| node.value > low));
+        assert!(high.is_none_or(|high| node.value < high));
+        let left = check(arena, node.left, low, Some(node.value));
+        let right = check(arena, node.right, Some(node.value), high);
+        assert!(left.abs_diff(right) <= 1, "unbalanced at {}", node.value);
+        assert_eq!(node.height, 1 + left.max(right));
+        node.height
+    }
+
+    #[test]
+    fn stays_balanced_on_sorted_insertions() {
+        let mut arena = TreeArena::default();
+        let mut root = NIL;
+        let mut collisions = 0;
+        for x in 0..1000 {
+            assert_eq!(
+                arena.insert(&mut root, &x, &mut collisions),
+                Ok(InsertOutcome::Inserted)
+            );
+        }
+        for x in (1000..2000).rev() {
+            arena.insert(&mut root, &x, &mut collisions).unwrap();
+        }
+        let height = check(&arena, root, None, None);
+        // an AVL tree with n nodes is at most about 1.44 log2(n) high
+        assert!(height <= 16, "height {}", height);
+        assert_eq!(
+            arena.insert(&mut root, &500, &mut collisions),
+            Ok(InsertOutcome::AlreadyPresent)
+        );
+    }
+
+    #[test]
+    fn lookups_need_logarithmic_collisions() {
+        let mut arena = TreeArena::default();
+        let mut root = NIL;
+        let mut uncounted = 0;
+        for x in 0..1024 {
+            arena.insert(&mut root, &(x * 2), &mut uncounted).unwrap();
+        }
+        for x in 0..2048 {
+            let mut collisions = 0;
+            assert_eq!(
+                arena.contains_counting(root, &x, &mut collisions),
+                x % 2 == 0
+            );
+            assert!(collisions <= 16, "{} collisions for {}", collisions, x);
+        }
+    }
+
+    #[test]
+    fn empty_tree() {
+        let arena = TreeArena::<u32>::default();
+        let mut collisions = 0;
+        assert!(!arena.contains_counting(NIL, &1, &mut collisions));
+        assert_eq!(collisions, 0);
+        assert_eq!(arena.heap_size(), 0);
+    }
+}
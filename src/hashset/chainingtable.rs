@@ -1,94 +1,362 @@
-use super::{HashTable, Hasher, ELEMENT_COUNT};
+use super::{
+    check_bucket, check_count, sizing, ChainOrganization, HashTable, HashTableError, Hasher,
+    InsertOutcome, InsertionPoint, InvariantViolation, Node, NodeArena, ProbeStats, Static,
+    TailInsertion, TreeArena, ELEMENT_COUNT, NIL,
+};
 use std::marker::PhantomData;
 use std::mem::size_of;
 
-/// Simple singly-linked List
+/// Direct chaining implementation of HashTable
 ///
-/// This implementation is not optimized for insertion performance
-/// but for size. The implementation is naive and should not be used
-/// for anything serious.
-pub enum LinkedList<T> {
-    Cons(T, Box<LinkedList<T>>),
-    Nil,
+/// Every bucket is the index of the first node of a chain that is used
+/// for collision resolution. The nodes of all chains are stored in one
+/// NodeArena. Up to u32::MAX - 1 elements can be inserted into this table.
+/// Chains can reorder themselves on successful lookups, see ChainOrganization
+pub struct DirectChainingTable<
+    T: PartialEq + Copy,
+    H: Hasher<T>,
+    I: InsertionPoint = TailInsertion,
+    O: ChainOrganization = Static,
+> {
+    collisions: usize,
+    insert_collisions: usize,
+    entries: Vec<u32>,
+    nodes: NodeArena<T>,
+    hasher: PhantomData<H>,
+    insertion: PhantomData<I>,
+    organization: PhantomData<O>,
+}
+impl<T: PartialEq + Copy, H: Hasher<T>, I: InsertionPoint, O: ChainOrganization> Default
+    for DirectChainingTable<T, H, I, O>
+{
+    /// initializes HashTable with ELEMENT_COUNT buckets
+    fn default() -> Self {
+        Self::with_size(ELEMENT_COUNT, 0)
+    }
+}
+
+impl<T: PartialEq + Copy, H: Hasher<T>, I: InsertionPoint, O: ChainOrganization>
+    DirectChainingTable<T, H, I, O>
+{
+    /// initializes HashTable with `size` buckets and room for `nodes` nodes
+    fn with_size(size: usize, nodes: usize) -> Self {
+        let mut entries = Vec::new();
+        entries.reserve_exact(size);
+        entries.resize(size, NIL);
+        Self {
+            collisions: 0,
+            insert_collisions: 0,
+            entries,
+            nodes: NodeArena::with_capacity(nodes),
+            hasher: PhantomData,
+            insertion: PhantomData,
+            organization: PhantomData,
+        }
+    }
 }
 
-impl<T: PartialEq + Copy> LinkedList<T> {
-    /// checks if an element is contained in the list
+impl<T: PartialEq + Copy, H: Hasher<T>, I: InsertionPoint, O: ChainOrganization> HashTable<T>
+    for DirectChainingTable<T, H, I, O>
+{
+    /// checks table for value
     ///
-    /// searches the LinkedList recursively with the canonical approach
-    pub fn contains(&self, searched: &T) -> bool {
-        match self {
-            LinkedList::Cons(val, other) => *val == *searched || other.contains(searched),
-            LinkedList::Nil => false,
+    /// checks by following the chain at the correct bucket.
+    /// Counts the number of collisions. If the value is found
+    /// the chain is reordered according to the chain organization
+    fn has(&mut self, val: &T) -> bool {
+        let index = H::hash(val, self.entries.len());
+        self.nodes
+            .find::<O>(&mut self.entries[index], val, &mut self.collisions)
+    }
+    /// checks the chain of the bucket for value, see `has`
+    ///
+    /// The chain is not reordered
+    fn probe_stats(&self, val: &T) -> ProbeStats {
+        let index = H::hash(val, self.entries.len());
+        let mut collisions = 0;
+        let found = self
+            .nodes
+            .contains_counting(self.entries[index], val, &mut collisions);
+        ProbeStats { found, collisions }
+    }
+    /// checks the chain of the bucket for value without counting collisions
+    fn contains(&self, val: &T) -> bool {
+        let index = H::hash(val, self.entries.len());
+        self.nodes.contains(self.entries[index], val)
+    }
+    /// resets number of collisions
+    fn reset_collisions(&mut self) {
+        self.collisions = 0;
+    }
+    /// returns number of collisions
+    fn get_collisions(&self) -> usize {
+        self.collisions
+    }
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
+    /// inserts the element into the HashTable
+    ///
+    /// only fails with `HashTableError::Full` if the arena can not index
+    /// another node. Every node of the chain checked before the value is
+    /// found or linked is counted as an insertion collision.
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
+        let index = H::hash(val, self.entries.len());
+        self.nodes
+            .insert::<I>(&mut self.entries[index], val, &mut self.insert_collisions)
+    }
+
+    /// resizes the number of buckets to specified byte value
+    ///
+    /// size of a direct chaining table is
+    /// buckets*(size of a bucket) + entries*(size of a node)
+    /// size of a bucket is the size of the index of the first node.
+    /// size of a node is the size of the element and the index of the next node.
+    /// (for T = u32) a bucket has 4 bytes and a node 8 bytes.
+    /// The arena is allocated for exactly `elements` nodes.
+    /// Fails if not even one bucket fits into the bytes left for buckets
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let node_size = size_of::<Node<T>>();
+        let bucket_size = size_of::<u32>();
+        let buckets = sizing::chained_buckets(bytes, elements, bucket_size, node_size)?;
+        *self = Self::with_size(buckets, elements);
+        Ok(())
+    }
+    /// returns the number of buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table, its buckets and the arena
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.entries.capacity() * size_of::<u32>() + self.nodes.heap_size()
+    }
+    /// checks that every node is in the chain of the bucket its element is hashed to
+    ///
+    /// Every node has to be reached from exactly one bucket, without
+    /// cycles, and no chain may store an element twice
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut visited = vec![false; self.nodes.node_count()];
+        for (bucket, head) in self.entries.iter().enumerate() {
+            let values = self.nodes.chain_values(*head, &mut visited)?;
+            check_bucket(bucket, &values, |x| {
+                H::hash(x, self.entries.len()) == bucket
+            })?;
+        }
+        check_count(
+            self.nodes.node_count(),
+            visited.iter().filter(|x| **x).count(),
+        )
+    }
+}
+
+/// Direct chaining implementation of HashTable with sorted chains
+///
+/// Like a DirectChainingTable, but every chain is sorted in ascending
+/// order. Failed lookups stop at the first element greater than the
+/// value searched, instead of traversing the whole chain.
+/// Up to u32::MAX - 1 elements can be inserted into this table
+pub struct SortedChainingTable<T: Ord + Copy, H: Hasher<T>> {
+    collisions: usize,
+    insert_collisions: usize,
+    entries: Vec<u32>,
+    nodes: NodeArena<T>,
+    hasher: PhantomData<H>,
+}
+impl<T: Ord + Copy, H: Hasher<T>> Default for SortedChainingTable<T, H> {
+    /// initializes HashTable with ELEMENT_COUNT buckets
+    fn default() -> Self {
+        Self::with_size(ELEMENT_COUNT, 0)
+    }
+}
+
+impl<T: Ord + Copy, H: Hasher<T>> SortedChainingTable<T, H> {
+    /// initializes HashTable with `size` buckets and room for `nodes` nodes
+    fn with_size(size: usize, nodes: usize) -> Self {
+        let mut entries = Vec::new();
+        entries.reserve_exact(size);
+        entries.resize(size, NIL);
+        Self {
+            collisions: 0,
+            insert_collisions: 0,
+            entries,
+            nodes: NodeArena::with_capacity(nodes),
+            hasher: PhantomData,
         }
     }
+}
 
-    /// inserts the Value as a new node at the end of the list
+impl<T: Ord + Copy, H: Hasher<T>> HashTable<T> for SortedChainingTable<T, H> {
+    /// checks table for value
     ///
-    /// This function is a really slow implementation. It was only
-    /// implemented this way because of simplicity
-    pub fn push(&mut self, val: T) {
-        let push_to_self = match self {
-            LinkedList::Cons(_, other) => {
-                other.push(val);
-                false
+    /// follows the sorted chain at the correct bucket until the value or
+    /// a greater element is found. Counts the number of collisions
+    fn has(&mut self, val: &T) -> bool {
+        let stats = self.probe_stats(val);
+        self.collisions += stats.collisions;
+        stats.found
+    }
+    /// checks the sorted chain of the bucket for value, see `has`
+    fn probe_stats(&self, val: &T) -> ProbeStats {
+        let index = H::hash(val, self.entries.len());
+        let mut collisions = 0;
+        let found = self
+            .nodes
+            .contains_sorted_counting(self.entries[index], val, &mut collisions);
+        ProbeStats { found, collisions }
+    }
+    /// checks the sorted chain of the bucket for value without counting collisions
+    fn contains(&self, val: &T) -> bool {
+        let index = H::hash(val, self.entries.len());
+        self.nodes.contains_sorted(self.entries[index], val)
+    }
+    /// resets number of collisions
+    fn reset_collisions(&mut self) {
+        self.collisions = 0;
+    }
+    /// returns number of collisions
+    fn get_collisions(&self) -> usize {
+        self.collisions
+    }
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
+    /// inserts the element into the HashTable
+    ///
+    /// only fails with `HashTableError::Full` if the arena can not index
+    /// another node. Every node of the chain smaller than the value is
+    /// counted as an insertion collision.
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
+        let index = H::hash(val, self.entries.len());
+        self.nodes
+            .insert_sorted(&mut self.entries[index], val, &mut self.insert_collisions)
+    }
+    /// resizes the number of buckets to specified byte value
+    ///
+    /// sized exactly like a DirectChainingTable.
+    /// Fails if not even one bucket fits into the bytes left for buckets
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let node_size = size_of::<Node<T>>();
+        let bucket_size = size_of::<u32>();
+        let buckets = sizing::chained_buckets(bytes, elements, bucket_size, node_size)?;
+        *self = Self::with_size(buckets, elements);
+        Ok(())
+    }
+    /// returns the number of buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table, its buckets and the arena
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.entries.capacity() * size_of::<u32>() + self.nodes.heap_size()
+    }
+    /// checks the chains like a DirectChainingTable and that they are sorted
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut visited = vec![false; self.nodes.node_count()];
+        for (bucket, head) in self.entries.iter().enumerate() {
+            let values = self.nodes.chain_values(*head, &mut visited)?;
+            check_bucket(bucket, &values, |x| {
+                H::hash(x, self.entries.len()) == bucket
+            })?;
+            if values.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err(InvariantViolation::Order { bucket });
             }
-            LinkedList::Nil => true,
-        };
-        if push_to_self {
-            *self = LinkedList::Cons(val, Box::new(LinkedList::Nil));
         }
+        check_count(
+            self.nodes.node_count(),
+            visited.iter().filter(|x| **x).count(),
+        )
     }
 }
 
-/// Direct chaining implementation of HashTable
+/// Separate chaining implementation of HashTable
 ///
-/// Every bucket is a pointer to a LinkedList that is used for
-/// collision resolution. An infinite amount of elements can be
-/// inserted into this table
-pub struct DirectChainingTable<T: PartialEq + Copy, H: Hasher<T>> {
+/// Every bucket stores one value and the index of the first node of a
+/// chain that is used for collision resolution. The nodes of all chains
+/// are stored in one NodeArena. Up to u32::MAX - 1 elements can
+/// overflow into the chains of this table.
+pub struct SeparateChainingTable<
+    T: PartialEq + Copy,
+    H: Hasher<T>,
+    I: InsertionPoint = TailInsertion,
+> {
     collisions: usize,
-    entries: Vec<Box<LinkedList<T>>>,
+    insert_collisions: usize,
+    entries: Vec<(Option<T>, u32)>,
+    nodes: NodeArena<T>,
     hasher: PhantomData<H>,
+    insertion: PhantomData<I>,
 }
-impl<T: PartialEq + Copy, H: Hasher<T>> Default for DirectChainingTable<T, H> {
+impl<T: PartialEq + Copy, H: Hasher<T>, I: InsertionPoint> Default
+    for SeparateChainingTable<T, H, I>
+{
     /// initializes HashTable with ELEMENT_COUNT buckets
     fn default() -> Self {
-        Self::with_size(ELEMENT_COUNT)
+        Self::with_size(ELEMENT_COUNT, 0)
     }
 }
 
-impl<T: PartialEq + Copy, H: Hasher<T>> DirectChainingTable<T, H> {
-    /// initializes HashTable with `size` buckets
-    fn with_size(size: usize) -> Self {
-        let mut entries = Vec::with_capacity(size);
-        for _ in 0..size {
-            entries.push(Box::new(LinkedList::Nil));
-        }
+impl<T: PartialEq + Copy, H: Hasher<T>, I: InsertionPoint> SeparateChainingTable<T, H, I> {
+    /// initializes HashTable with `size` buckets and room for `nodes` nodes
+    fn with_size(size: usize, nodes: usize) -> Self {
+        let mut entries = Vec::new();
+        entries.reserve_exact(size);
+        entries.resize(size, (None, NIL));
         Self {
             collisions: 0,
+            insert_collisions: 0,
             entries,
+            nodes: NodeArena::with_capacity(nodes),
             hasher: PhantomData,
+            insertion: PhantomData,
         }
     }
 }
 
-impl<T: PartialEq + Copy, H: Hasher<T>> HashTable<T> for DirectChainingTable<T, H> {
+impl<T: PartialEq + Copy, H: Hasher<T>, I: InsertionPoint> HashTable<T>
+    for SeparateChainingTable<T, H, I>
+{
     /// checks table for value
     ///
-    /// checks by checking the LinkedList at the correct bucket.
+    /// checks by checking first checking the value stored at the
+    /// correct bucket, then following the associated chain.
     /// Counts the number of collisions
     fn has(&mut self, val: &T) -> bool {
+        let stats = self.probe_stats(val);
+        self.collisions += stats.collisions;
+        stats.found
+    }
+    /// checks the bucket and its overflow chain for value, see `has`
+    fn probe_stats(&self, val: &T) -> ProbeStats {
         let index = H::hash(val, self.entries.len());
-        let mut right_now = self.entries[index].as_ref();
-        while let LinkedList::Cons(x, next) = right_now {
-            if *x == *val {
-                return true;
+        let (inline, head) = self.entries[index];
+        let mut collisions = 0;
+        if let Some(x) = inline {
+            if x == *val {
+                return ProbeStats {
+                    found: true,
+                    collisions,
+                };
             }
-            self.collisions += 1;
-            right_now = next.as_ref();
+            collisions += 1;
         }
-        false
+        let found = self.nodes.contains_counting(head, val, &mut collisions);
+        ProbeStats { found, collisions }
+    }
+    /// checks the bucket and its overflow chain for value without counting collisions
+    fn contains(&self, val: &T) -> bool {
+        let index = H::hash(val, self.entries.len());
+        let (inline, head) = self.entries[index];
+        inline == Some(*val) || self.nodes.contains(head, val)
     }
     /// resets number of collisions
     fn reset_collisions(&mut self) {
@@ -98,90 +366,173 @@ impl<T: PartialEq + Copy, H: Hasher<T>> HashTable<T> for DirectChainingTable<T,
     fn get_collisions(&self) -> usize {
         self.collisions
     }
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
     /// inserts the element into the HashTable
     ///
-    /// always returns true as it won't fail
-    fn insert(&mut self, val: &T) -> bool {
+    /// only fails with `HashTableError::Full` if the arena can not index
+    /// another node. The value stored in the bucket and every node of the
+    /// chain checked before the value is found or linked is counted as an
+    /// insertion collision.
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
         let index = H::hash(val, self.entries.len());
-        if !self.entries[index].contains(val) {
-            self.entries[index].push(*val);
+        match self.entries[index].0 {
+            None => {
+                self.entries[index].0 = Some(*val);
+                return Ok(InsertOutcome::Inserted);
+            }
+            Some(x) if x == *val => return Ok(InsertOutcome::AlreadyPresent),
+            Some(_) => self.insert_collisions += 1,
         }
-        true
+        self.nodes
+            .insert::<I>(&mut self.entries[index].1, val, &mut self.insert_collisions)
     }
-
     /// resizes the number of buckets to specified byte value
     ///
-    /// size of a direct chaining table is
-    /// buckets*(size of a bucket) + entries*(size of a node)
-    /// (for T = u32) size of a bucket is the size of a
-    /// pointer to a LinkedList = 8
-    /// size of a node is the size of a LinkedList node = 16
-    /// Fails if bytes - 16*elements < 8
-    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) {
-        let list_size = size_of::<LinkedList<T>>();
-        let available_bytes = bytes as isize - (list_size * elements) as isize;
-        if available_bytes < size_of::<Box<LinkedList<T>>>() as isize {
-            panic!("not enough bytes available for the buckets");
+    /// The size of a separate chaining table is
+    /// buckets*(size of a bucket) + overflowing elements*(size of a node)
+    /// size of a bucket is the size of the inline value and the index
+    /// of the first node. size of a node is the size of the element and
+    /// the index of the next node.
+    /// Elements overflow if their bucket is occupied already, so the number of
+    /// overflowing elements is approximated with n - (m - m((m-1)/m)^n).
+    /// The arena is allocated for the expected number of overflowing elements.
+    /// Will fail if no suitable value m is found.
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let node_size = size_of::<Node<T>>();
+        let bucket_size = size_of::<(Option<T>, u32)>();
+        let buckets = sizing::separate_buckets(bytes, elements, bucket_size, node_size)?;
+        let nodes = sizing::expected_overflowing(buckets, elements, 1).ceil() as usize;
+        *self = Self::with_size(buckets, nodes);
+        Ok(())
+    }
+    /// returns the number of buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table, its buckets and the arena
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.entries.capacity() * size_of::<(Option<T>, u32)>()
+            + self.nodes.heap_size()
+    }
+    /// checks the buckets and chains like a DirectChainingTable
+    ///
+    /// Only buckets storing an element inline may have a chain
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut visited = vec![false; self.nodes.node_count()];
+        for (bucket, (inline, head)) in self.entries.iter().enumerate() {
+            if inline.is_none() && *head != NIL {
+                return Err(InvariantViolation::Layout { bucket });
+            }
+            let mut values: Vec<T> = inline.iter().copied().collect();
+            values.extend(self.nodes.chain_values(*head, &mut visited)?);
+            check_bucket(bucket, &values, |x| {
+                H::hash(x, self.entries.len()) == bucket
+            })?;
         }
-        *self = Self::with_size(available_bytes as usize / size_of::<Box<LinkedList<T>>>());
+        check_count(
+            self.nodes.node_count(),
+            visited.iter().filter(|x| **x).count(),
+        )
     }
 }
 
-/// Separate chaining implementation of HashTable
+/// Bucketized chaining implementation of HashTable
 ///
-/// Every bucket has stores one value and a pointer to a LinkedList
-/// that is used for collision resolution. An infinite amount of
-/// elements can be inserted into this table.
-pub struct SeparateChainingTable<T: PartialEq + Copy, H: Hasher<T>> {
+/// Every bucket stores up to N values inline and the index of the first
+/// node of a chain that is used for collision resolution once the bucket
+/// is full. The values of a bucket are stored in order, so the first
+/// empty slot ends every search. With N = 1 this is a SeparateChainingTable.
+/// Up to u32::MAX - 1 elements can overflow into the chains of this table.
+pub struct BucketizedChainingTable<T: PartialEq + Copy, H: Hasher<T>, const N: usize> {
     collisions: usize,
-    entries: Vec<(Option<T>, Box<LinkedList<T>>)>,
+    insert_collisions: usize,
+    entries: Vec<([Option<T>; N], u32)>,
+    nodes: NodeArena<T>,
     hasher: PhantomData<H>,
 }
-impl<T: PartialEq + Copy, H: Hasher<T>> Default for SeparateChainingTable<T, H> {
+impl<T: PartialEq + Copy, H: Hasher<T>, const N: usize> Default
+    for BucketizedChainingTable<T, H, N>
+{
     /// initializes HashTable with ELEMENT_COUNT buckets
     fn default() -> Self {
-        Self::with_size(ELEMENT_COUNT)
+        Self::with_size(ELEMENT_COUNT, 0)
     }
 }
 
-impl<T: PartialEq + Copy, H: Hasher<T>> SeparateChainingTable<T, H> {
-    /// initializes HashTable with `size` buckets
-    fn with_size(size: usize) -> Self {
-        let mut entries = Vec::with_capacity(size);
-        for _ in 0..size {
-            entries.push((None, Box::new(LinkedList::Nil)));
-        }
+impl<T: PartialEq + Copy, H: Hasher<T>, const N: usize> BucketizedChainingTable<T, H, N> {
+    /// initializes HashTable with `size` buckets and room for `nodes` nodes
+    fn with_size(size: usize, nodes: usize) -> Self {
+        let mut entries = Vec::new();
+        entries.reserve_exact(size);
+        entries.resize(size, ([None; N], NIL));
         Self {
             collisions: 0,
+            insert_collisions: 0,
             entries,
+            nodes: NodeArena::with_capacity(nodes),
             hasher: PhantomData,
         }
     }
 }
 
-impl<T: PartialEq + Copy, H: Hasher<T>> HashTable<T> for SeparateChainingTable<T, H> {
+impl<T: PartialEq + Copy, H: Hasher<T>, const N: usize> HashTable<T>
+    for BucketizedChainingTable<T, H, N>
+{
     /// checks table for value
     ///
-    /// checks by checking first checking the value stored at the
-    /// correct bucket, then checking the associated LinkedList.
+    /// checks the values stored at the correct bucket first. If every
+    /// slot of the bucket is occupied the associated chain is followed.
     /// Counts the number of collisions
     fn has(&mut self, val: &T) -> bool {
+        let stats = self.probe_stats(val);
+        self.collisions += stats.collisions;
+        stats.found
+    }
+    /// checks the slots of the bucket and its overflow chain for value, see `has`
+    fn probe_stats(&self, val: &T) -> ProbeStats {
         let index = H::hash(val, self.entries.len());
-        if let Some(x) = self.entries[index].0 {
-            if x == *val {
-                return true;
+        let (inline, head) = &self.entries[index];
+        let mut collisions = 0;
+        for slot in inline {
+            match slot {
+                Some(x) if *x == *val => {
+                    return ProbeStats {
+                        found: true,
+                        collisions,
+                    }
+                }
+                Some(_) => collisions += 1,
+                None => {
+                    return ProbeStats {
+                        found: false,
+                        collisions,
+                    }
+                }
             }
-            self.collisions += 1;
         }
-        let mut right_now = self.entries[index].1.as_ref();
-        while let LinkedList::Cons(x, new) = right_now {
-            if *x == *val {
-                return true;
+        let found = self.nodes.contains_counting(*head, val, &mut collisions);
+        ProbeStats { found, collisions }
+    }
+    /// checks the slots and the overflow chain for value without counting collisions
+    fn contains(&self, val: &T) -> bool {
+        let index = H::hash(val, self.entries.len());
+        let (inline, head) = &self.entries[index];
+        for slot in inline {
+            match slot {
+                Some(x) if *x == *val => return true,
+                Some(_) => {}
+                None => return false,
             }
-            self.collisions += 1;
-            right_now = new.as_ref();
         }
-        false
+        self.nodes.contains(*head, val)
     }
     /// resets number of collisions
     fn reset_collisions(&mut self) {
@@ -191,59 +542,525 @@ impl<T: PartialEq + Copy, H: Hasher<T>> HashTable<T> for SeparateChainingTable<T
     fn get_collisions(&self) -> usize {
         self.collisions
     }
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
     /// inserts the element into the HashTable
     ///
-    /// always returns true as it won't fail
-    fn insert(&mut self, val: &T) -> bool {
+    /// only fails with `HashTableError::Full` if the arena can not index
+    /// another node. The value is stored in the first empty slot of the
+    /// bucket, if every slot is occupied it is appended to the chain.
+    /// Every value in the bucket and every node of the chain checked before
+    /// the value is found or stored is counted as an insertion collision.
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
         let index = H::hash(val, self.entries.len());
-        if self.entries[index].0.is_none() {
-            self.entries[index].0 = Some(*val);
-            return true;
+        let (inline, head) = &mut self.entries[index];
+        for slot in inline.iter_mut() {
+            match slot {
+                Some(x) if *x == *val => return Ok(InsertOutcome::AlreadyPresent),
+                Some(_) => self.insert_collisions += 1,
+                None => {
+                    *slot = Some(*val);
+                    return Ok(InsertOutcome::Inserted);
+                }
+            }
         }
-        if let Some(x) = self.entries[index].0 {
-            if x == *val {
-                return true;
+        self.nodes
+            .insert::<TailInsertion>(head, val, &mut self.insert_collisions)
+    }
+    /// resizes the number of buckets to specified byte value
+    ///
+    /// The size of a bucketized chaining table is
+    /// buckets*(size of a bucket) + overflowing elements*(size of a node)
+    /// size of a bucket is the size of N inline values and the index
+    /// of the first node. size of a node is the size of the element and
+    /// the index of the next node.
+    /// Elements overflow if their bucket is full already, the expected
+    /// number of overflowing elements is calculated by `sizing::expected_overflowing`.
+    /// The arena is allocated for the expected number of overflowing elements.
+    /// Will fail if no suitable number of buckets is found.
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let node_size = size_of::<Node<T>>();
+        let bucket_size = size_of::<([Option<T>; N], u32)>();
+        let buckets = sizing::bucketized_buckets(bytes, elements, N, bucket_size, node_size)?;
+        let nodes = sizing::expected_overflowing(buckets, elements, N).ceil() as usize;
+        *self = Self::with_size(buckets, nodes);
+        Ok(())
+    }
+    /// returns the number of buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table, its buckets and the arena
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.entries.capacity() * size_of::<([Option<T>; N], u32)>()
+            + self.nodes.heap_size()
+    }
+    /// checks the buckets and chains like a DirectChainingTable
+    ///
+    /// The slots of a bucket have to be filled in order and only full
+    /// buckets may have a chain
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut visited = vec![false; self.nodes.node_count()];
+        for (bucket, (inline, head)) in self.entries.iter().enumerate() {
+            let used = inline.iter().take_while(|slot| slot.is_some()).count();
+            if inline[used..].iter().any(Option::is_some) || (used < N && *head != NIL) {
+                return Err(InvariantViolation::Layout { bucket });
             }
+            let mut values: Vec<T> = inline.iter().flatten().copied().collect();
+            values.extend(self.nodes.chain_values(*head, &mut visited)?);
+            check_bucket(bucket, &values, |x| {
+                H::hash(x, self.entries.len()) == bucket
+            })?;
         }
-        if self.entries[index].1.contains(val) {
-            return true;
+        check_count(
+            self.nodes.node_count(),
+            visited.iter().filter(|x| **x).count(),
+        )
+    }
+}
+
+/// Bucket of a TreeifiedChainingTable
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TreeBucket {
+    /// index of the first node of a chain
+    Chain(u32),
+    /// index of the root of an AVL tree
+    Tree(u32),
+}
+
+/// Chaining implementation of HashTable with balanced trees for long chains
+///
+/// Every bucket starts as a chain like in a DirectChainingTable. Once
+/// a chain holds more than THRESHOLD elements it is converted into an
+/// AVL tree, so a lookup in a bucket with n elements needs at most
+/// O(log n) comparisons even if many elements are hashed to the same bucket.
+/// Nodes of converted chains are reused by later chains. Up to u32::MAX - 1
+/// elements can be inserted into this table
+pub struct TreeifiedChainingTable<T: Ord + Copy, H: Hasher<T>, const THRESHOLD: usize = 8> {
+    collisions: usize,
+    insert_collisions: usize,
+    entries: Vec<TreeBucket>,
+    nodes: NodeArena<T>,
+    trees: TreeArena<T>,
+    hasher: PhantomData<H>,
+}
+impl<T: Ord + Copy, H: Hasher<T>, const THRESHOLD: usize> Default
+    for TreeifiedChainingTable<T, H, THRESHOLD>
+{
+    /// initializes HashTable with ELEMENT_COUNT buckets
+    fn default() -> Self {
+        Self::with_size(ELEMENT_COUNT, 0)
+    }
+}
+
+impl<T: Ord + Copy, H: Hasher<T>, const THRESHOLD: usize> TreeifiedChainingTable<T, H, THRESHOLD> {
+    /// initializes HashTable with `size` buckets and room for `nodes` nodes
+    fn with_size(size: usize, nodes: usize) -> Self {
+        let mut entries = Vec::new();
+        entries.reserve_exact(size);
+        entries.resize(size, TreeBucket::Chain(NIL));
+        Self {
+            collisions: 0,
+            insert_collisions: 0,
+            entries,
+            nodes: NodeArena::with_capacity(nodes),
+            trees: TreeArena::default(),
+            hasher: PhantomData,
+        }
+    }
+
+    /// converts the chain of the bucket at `index` into a tree
+    ///
+    /// The tree arena has to have room for every node of the chain.
+    /// The nodes of the chain are released. Comparisons while building
+    /// the tree are not counted as collisions
+    fn treeify(&mut self, index: usize) {
+        let head = match self.entries[index] {
+            TreeBucket::Chain(head) => head,
+            TreeBucket::Tree(_) => return,
+        };
+        let mut root = NIL;
+        let mut uncounted = 0;
+        let mut next = head;
+        while next != NIL {
+            let node = *self.nodes.get(next);
+            self.trees
+                .insert(&mut root, &node.value, &mut uncounted)
+                .expect("room for the chain was checked before");
+            next = node.next;
+        }
+        self.nodes.release(head);
+        self.entries[index] = TreeBucket::Tree(root);
+    }
+}
+
+impl<T: Ord + Copy, H: Hasher<T>, const THRESHOLD: usize> HashTable<T>
+    for TreeifiedChainingTable<T, H, THRESHOLD>
+{
+    /// checks table for value
+    ///
+    /// checks by following the chain or searching the tree at the correct
+    /// bucket. Counts the number of collisions
+    fn has(&mut self, val: &T) -> bool {
+        let stats = self.probe_stats(val);
+        self.collisions += stats.collisions;
+        stats.found
+    }
+    /// checks the chain or tree of the bucket for value, see `has`
+    fn probe_stats(&self, val: &T) -> ProbeStats {
+        let index = H::hash(val, self.entries.len());
+        let mut collisions = 0;
+        let found = match self.entries[index] {
+            TreeBucket::Chain(head) => self.nodes.contains_counting(head, val, &mut collisions),
+            TreeBucket::Tree(root) => self.trees.contains_counting(root, val, &mut collisions),
+        };
+        ProbeStats { found, collisions }
+    }
+    /// checks the chain or tree of the bucket for value without counting collisions
+    fn contains(&self, val: &T) -> bool {
+        let index = H::hash(val, self.entries.len());
+        match self.entries[index] {
+            TreeBucket::Chain(head) => self.nodes.contains(head, val),
+            TreeBucket::Tree(root) => self.trees.contains(root, val),
+        }
+    }
+    /// resets number of collisions
+    fn reset_collisions(&mut self) {
+        self.collisions = 0;
+    }
+    /// returns number of collisions
+    fn get_collisions(&self) -> usize {
+        self.collisions
+    }
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
+    /// inserts the element into the HashTable
+    ///
+    /// only fails with `HashTableError::Full` if an arena can not index
+    /// another node, or if the chain would be converted and the tree arena
+    /// could not index its nodes. The table is not changed then. Every node of the chain or
+    /// tree checked before the value is found or linked is counted as an
+    /// insertion collision. A chain is converted into a tree once it holds
+    /// more than THRESHOLD elements
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
+        let index = H::hash(val, self.entries.len());
+        match &mut self.entries[index] {
+            TreeBucket::Tree(root) => self.trees.insert(root, val, &mut self.insert_collisions),
+            TreeBucket::Chain(head) => {
+                // checked before linking, so a failed conversion can not leave the value behind.
+                // Only a nearly full tree arena needs the length of the chain
+                if !self.trees.has_room(THRESHOLD + 1) {
+                    let mut len = 0;
+                    let present = self.nodes.contains_counting(*head, val, &mut len);
+                    if !present && len >= THRESHOLD && !self.trees.has_room(len + 1) {
+                        return Err(HashTableError::Full);
+                    }
+                }
+                let before = self.insert_collisions;
+                let outcome =
+                    self.nodes
+                        .insert::<TailInsertion>(head, val, &mut self.insert_collisions)?;
+                // every node of the chain was checked before the value was appended
+                let len = self.insert_collisions - before + 1;
+                if outcome == InsertOutcome::Inserted && len > THRESHOLD {
+                    self.treeify(index);
+                }
+                Ok(outcome)
+            }
         }
-        self.entries[index].1.push(*val);
-        true
     }
     /// resizes the number of buckets to specified byte value
     ///
-    /// Warning: Is correct for T=u32 only.
-    /// (for T=u32) The size of a separate chaining table is
-    /// elements*16 + empty_buckets*16
-    /// The number of empty buckets is approximated with m((m-1)/m)^n
-    /// As this equation grows monotonically in m, the value can be found
-    /// by using a variant of binary search.
-    /// Will fail if no suitable value m is found.
-    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) {
-        // to make calculations simpler we do not calculate in terms of
-        // bytes but in terms of 16B always
-        let available_elements = (bytes / 16) as f64;
-
-        let elements = elements as f64;
-        // We cannot allocate more buckets than with zero collisions
-        let mut buckets = available_elements as f64;
-        let mut step = available_elements as f64 / 2_f64;
-        // do to an accuracy of 1
-        while step > 1_f64 {
-            let used_elements = buckets * ((buckets - 1_f64) / buckets).powf(elements) + elements;
-            if used_elements > available_elements {
-                // found a maximum, go lower
-                buckets -= step;
-            } else if used_elements < available_elements {
-                // found a minimum, can lower step size
-                step = step / 2_f64;
-                buckets += step;
+    /// sized like a DirectChainingTable, with a bucket being the size of
+    /// the chain or tree index. (for T = u32) a bucket has 8 bytes and a
+    /// node 8 bytes. Trees are not accounted for, as hardly any chain
+    /// grows long enough to be converted with a good hash function.
+    /// Fails if not even one bucket fits into the bytes left for buckets
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let node_size = size_of::<Node<T>>();
+        let bucket_size = size_of::<TreeBucket>();
+        let buckets = sizing::chained_buckets(bytes, elements, bucket_size, node_size)?;
+        *self = Self::with_size(buckets, elements);
+        Ok(())
+    }
+    /// returns the number of buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table, its buckets and both arenas
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.entries.capacity() * size_of::<TreeBucket>()
+            + self.nodes.heap_size()
+            + self.trees.heap_size()
+    }
+    /// checks the chains like a DirectChainingTable and the trees like AVL trees
+    ///
+    /// Chains may hold at most THRESHOLD elements, trees more. Every node
+    /// of both arenas has to be reached exactly once, nodes of converted
+    /// chains from the free list
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut chained = vec![false; self.nodes.node_count()];
+        let mut treed = vec![false; self.trees.node_count()];
+        self.nodes
+            .chain_values(self.nodes.free_list(), &mut chained)?;
+        for (bucket, entry) in self.entries.iter().enumerate() {
+            let values = match *entry {
+                TreeBucket::Chain(head) => self.nodes.chain_values(head, &mut chained)?,
+                TreeBucket::Tree(root) => self.trees.tree_values(bucket, root, &mut treed)?,
+            };
+            let is_tree = matches!(entry, TreeBucket::Tree(_));
+            if is_tree != (values.len() > THRESHOLD) {
+                return Err(InvariantViolation::Layout { bucket });
             }
+            check_bucket(bucket, &values, |x| {
+                H::hash(x, self.entries.len()) == bucket
+            })?;
+        }
+        check_count(
+            self.nodes.node_count(),
+            chained.iter().filter(|x| **x).count(),
+        )?;
+        check_count(
+            self.trees.node_count(),
+            treed.iter().filter(|x| **x).count(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::lookup;
+    use super::super::{HeadInsertion, ModHash, MoveToFront, Transpose, XorShiftHash};
+    use super::*;
+
+    /// inserts every value, which must not be in the table yet
+    fn insert_all<H: HashTable<u32>>(table: &mut H, values: &[u32]) {
+        for x in values {
+            assert_eq!(table.insert(x), Ok(InsertOutcome::Inserted));
+        }
+    }
+
+    #[test]
+    fn direct_chains_collide_in_one_bucket() {
+        let mut table = DirectChainingTable::<u32, ModHash>::with_size(4, 0);
+        // 0, 4, 8 and 12 are chained in bucket 0
+        insert_all(&mut table, &[0, 4, 8, 12, 1]);
+        assert_eq!(table.get_insert_collisions(), 1 + 2 + 3);
+        assert_eq!(lookup(&table, 12), 3);
+        assert_eq!(lookup(&table, 16), 4);
+        assert_eq!(lookup(&table, 1), 0);
+        assert_eq!(table.insert(&8), Ok(InsertOutcome::AlreadyPresent));
+
+        let mut table = DirectChainingTable::<u32, ModHash, HeadInsertion>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8, 12]);
+        assert_eq!(table.get_insert_collisions(), 1 + 2 + 3);
+        assert_eq!(lookup(&table, 12), 0);
+        assert_eq!(lookup(&table, 0), 3);
+    }
+
+    #[test]
+    fn self_organizing_chains() {
+        let mut table =
+            DirectChainingTable::<u32, ModHash, TailInsertion, MoveToFront>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8, 12]);
+        assert!(table.has(&12));
+        assert_eq!(table.get_collisions(), 3);
+        // probe_stats does not reorder, has does
+        assert_eq!(lookup(&table, 12), 0);
+        assert_eq!(lookup(&table, 0), 1);
+
+        let mut table =
+            DirectChainingTable::<u32, ModHash, TailInsertion, Transpose>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8, 12]);
+        assert!(table.has(&12));
+        assert_eq!(lookup(&table, 12), 2);
+        assert_eq!(lookup(&table, 8), 3);
+    }
+
+    #[test]
+    fn sorted_chains_stop_early() {
+        let mut table = SortedChainingTable::<u32, ModHash>::with_size(4, 0);
+        insert_all(&mut table, &[12, 0, 8, 4]);
+        assert_eq!(lookup(&table, 0), 0);
+        assert_eq!(lookup(&table, 12), 3);
+        // 6 is not in bucket 0 but 2
+        assert_eq!(lookup(&table, 6), 0);
+        // stops at 8, the first element greater than 5 in bucket 1
+        insert_all(&mut table, &[1, 9]);
+        assert!(!table.has(&5));
+        assert_eq!(table.get_collisions(), 2);
+    }
+
+    #[test]
+    fn separate_chains_start_inline() {
+        let mut table = SeparateChainingTable::<u32, ModHash>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8]);
+        assert_eq!(lookup(&table, 0), 0);
+        assert_eq!(lookup(&table, 4), 1);
+        assert_eq!(lookup(&table, 8), 2);
+        assert_eq!(lookup(&table, 12), 3);
+        assert_eq!(lookup(&table, 1), 0);
+        assert_eq!(table.insert(&4), Ok(InsertOutcome::AlreadyPresent));
+    }
+
+    #[test]
+    fn bucketized_chains_fill_buckets_first() {
+        let mut table = BucketizedChainingTable::<u32, ModHash, 2>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8, 12]);
+        assert_eq!(lookup(&table, 0), 0);
+        assert_eq!(lookup(&table, 4), 1);
+        assert_eq!(lookup(&table, 8), 2);
+        assert_eq!(lookup(&table, 12), 3);
+        // the first empty slot ends the search
+        assert_eq!(lookup(&table, 1), 0);
+        insert_all(&mut table, &[1]);
+        assert_eq!(lookup(&table, 5), 1);
+    }
+
+    #[test]
+    fn long_chains_are_treeified() {
+        let mut table = TreeifiedChainingTable::<u32, ModHash, 4>::with_size(4, 0);
+        let values: Vec<u32> = (0..256).map(|x| x * 4).collect();
+        insert_all(&mut table, &values);
+        assert!(matches!(table.entries[0], TreeBucket::Tree(_)));
+        assert!(matches!(table.entries[1], TreeBucket::Chain(NIL)));
+        for x in &values {
+            assert!(
+                lookup(&table, *x) <= 12,
+                "{} collisions",
+                lookup(&table, *x)
+            );
+            assert_eq!(table.insert(x), Ok(InsertOutcome::AlreadyPresent));
         }
-        if buckets < 1_f64 {
-            panic!("invalid configuration for direct chaining table");
+        assert!(!table.contains(&1024));
+        // the nodes of the converted chain were released and are reused
+        let nodes = table.nodes.node_count();
+        insert_all(&mut table, &[1, 5, 9]);
+        assert_eq!(table.nodes.node_count(), nodes);
+        assert_eq!(table.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn short_chains_stay_chains() {
+        let mut table = TreeifiedChainingTable::<u32, ModHash, 4>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8, 12]);
+        assert!(matches!(table.entries[0], TreeBucket::Chain(_)));
+        insert_all(&mut table, &[16]);
+        assert!(matches!(table.entries[0], TreeBucket::Tree(_)));
+
+        // no tree arena can index more nodes than this threshold, but no chain gets that long
+        let mut table =
+            TreeifiedChainingTable::<u32, ModHash, { u32::MAX as usize }>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8, 12, 16, 1]);
+        assert_eq!(table.insert(&4), Ok(InsertOutcome::AlreadyPresent));
+        assert!(matches!(table.entries[0], TreeBucket::Chain(_)));
+    }
+
+    #[test]
+    fn resizing_keeps_the_budget() {
+        let bytes = 1 << 16;
+        let elements = 1000;
+        let mut tables: Vec<Box<dyn HashTable<u32>>> = vec![
+            Box::new(DirectChainingTable::<u32, XorShiftHash>::default()),
+            Box::new(SortedChainingTable::<u32, XorShiftHash>::default()),
+            Box::new(SeparateChainingTable::<u32, XorShiftHash>::default()),
+            Box::new(BucketizedChainingTable::<u32, XorShiftHash, 4>::default()),
+            Box::new(TreeifiedChainingTable::<u32, XorShiftHash>::default()),
+        ];
+        for table in tables.iter_mut() {
+            table.resize_to_bytes(bytes, elements).unwrap();
+            for x in 0..elements as u32 {
+                table.insert(&x).unwrap();
+            }
+            let budget = bytes + size_of_val(table.as_ref());
+            // chained tables with nodes for exactly `elements` only grow on overflow
+            assert!(table.memory_usage() <= budget * 11 / 10);
+            assert!(table.resize_to_bytes(16, elements).is_err());
         }
-        *self = Self::with_size(buckets as usize);
+    }
+
+    #[test]
+    fn detects_broken_chains() {
+        let mut table = DirectChainingTable::<u32, ModHash>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8, 1]);
+        assert_eq!(table.check_invariants(), Ok(()));
+
+        // 0 -> 4 -> 8 -> 0
+        table.nodes.get_mut(2).next = 0;
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Cycle { index: 0 })
+        );
+        table.nodes.get_mut(2).next = NIL;
+        table.entries.swap(0, 1);
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Unreachable { bucket: 0 })
+        );
+        table.entries.swap(0, 1);
+        table.nodes.get_mut(1).value = 0;
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Duplicate { bucket: 0 })
+        );
+        table.nodes.get_mut(1).value = 4;
+        table.entries[3] = 7;
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::OutOfBounds { index: 7 })
+        );
+        table.entries[3] = NIL;
+        table.entries[1] = NIL;
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Count {
+                expected: 4,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn detects_unsorted_chains_and_bad_layouts() {
+        let mut table = SortedChainingTable::<u32, ModHash>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8]);
+        assert_eq!(table.check_invariants(), Ok(()));
+        table.nodes.get_mut(table.entries[0]).value = 12;
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Order { bucket: 0 })
+        );
+
+        let mut table = BucketizedChainingTable::<u32, ModHash, 2>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8]);
+        assert_eq!(table.check_invariants(), Ok(()));
+        table.entries[0].0[0] = None;
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Layout { bucket: 0 })
+        );
+
+        let mut table = TreeifiedChainingTable::<u32, ModHash, 2>::with_size(4, 0);
+        insert_all(&mut table, &[0, 4, 8, 12]);
+        assert_eq!(table.check_invariants(), Ok(()));
+        table.entries[1] = std::mem::replace(&mut table.entries[0], TreeBucket::Chain(NIL));
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Unreachable { bucket: 1 })
+        );
     }
 }
@@ -1,64 +1,198 @@
-use super::{HashTable, Hasher, ELEMENT_COUNT};
+use super::{
+    check_probe_path, sizing, HashTable, HashTableBuilder, HashTableError, Hasher, InsertOutcome,
+    InvariantViolation, ProbeStats, ELEMENT_COUNT,
+};
 use std::marker::PhantomData;
 
+/// Fraction of the buckets used as address region by variants with a cellar
+///
+/// The address factor β = address region / all buckets. 0.86 is the
+/// optimum found by Vitter for successful lookups in a nearly full table
+pub const DEFAULT_ADDRESS_FACTOR: f64 = 0.86;
+
+/// Variant of coalesced hashing used by a CoalescedTable
+///
+/// Variants differ in where a new element is linked into its chain and
+/// in whether a cellar is reserved for overflowing elements. The cellar
+/// is a region of buckets no element is hashed to, so overflowing
+/// elements do not occupy buckets of the address region.
+pub trait CoalescingStrategy {
+    /// Link new elements directly after the bucket they are hashed to
+    /// instead of at the end of the chain
+    const EARLY_INSERTION: bool;
+    /// Reserve a cellar of (1 - DEFAULT_ADDRESS_FACTOR) of all buckets,
+    /// unless another address factor is given
+    const CELLAR: bool;
+}
+
+/// Late-insertion standard coalesced hashing
+///
+/// New elements are appended at the end of the chain, there is no cellar
+pub struct Lisch;
+impl CoalescingStrategy for Lisch {
+    const EARLY_INSERTION: bool = false;
+    const CELLAR: bool = false;
+}
+
+/// Early-insertion standard coalesced hashing
+///
+/// New elements are linked directly after the bucket they are hashed to,
+/// there is no cellar
+pub struct Eisch;
+impl CoalescingStrategy for Eisch {
+    const EARLY_INSERTION: bool = true;
+    const CELLAR: bool = false;
+}
+
+/// Late-insertion coalesced hashing
+///
+/// New elements are appended at the end of the chain, overflowing
+/// elements are stored in the cellar first
+pub struct Lich;
+impl CoalescingStrategy for Lich {
+    const EARLY_INSERTION: bool = false;
+    const CELLAR: bool = true;
+}
+
+/// Early-insertion coalesced hashing
+///
+/// New elements are linked directly after the bucket they are hashed to,
+/// overflowing elements are stored in the cellar first
+pub struct Eich;
+impl CoalescingStrategy for Eich {
+    const EARLY_INSERTION: bool = true;
+    const CELLAR: bool = true;
+}
+
 /// HashTable with coalesced buckets for collision resolution
 ///
 /// Every buckets saves an Element and an optional pointer to
 /// the next bucket used for collision resolution.
-pub struct CoalescedTable<T: PartialEq + Copy, H: Hasher<T>> {
+/// Elements are only hashed to the address region at the start of the
+/// table, the cellar after it is only used for overflowing elements.
+/// Overflowing elements are stored in the empty bucket with the highest index,
+/// so the cellar is used up before the address region.
+/// The size of the address region is the address factor times the number of buckets.
+pub struct CoalescedTable<T: PartialEq + Copy, H: Hasher<T>, S: CoalescingStrategy = Lisch> {
     collisions: usize,
+    insert_collisions: usize,
     entries: Vec<Option<(T, Option<usize>)>>,
+    address_factor: f64,
+    address_buckets: usize,
     hasher: PhantomData<H>,
+    strategy: PhantomData<S>,
     cursor: usize,
 }
-impl<T: PartialEq + Copy, H: Hasher<T>> Default for CoalescedTable<T, H> {
+impl<T: PartialEq + Copy, H: Hasher<T>, S: CoalescingStrategy> Default for CoalescedTable<T, H, S> {
     /// initializes HashTable with ELEMENT_COUNT buckets
     fn default() -> Self {
         Self::with_size(ELEMENT_COUNT)
     }
 }
 
-impl<T: PartialEq + Copy, H: Hasher<T>> CoalescedTable<T, H> {
-    /// initializes HashTable with `size` buckets
+impl<T: PartialEq + Copy, H: Hasher<T>, S: CoalescingStrategy> CoalescedTable<T, H, S> {
+    /// initializes HashTable with `size` buckets, including the cellar
+    ///
+    /// uses the default address factor of the strategy
     fn with_size(size: usize) -> Self {
+        let address_factor = if S::CELLAR {
+            DEFAULT_ADDRESS_FACTOR
+        } else {
+            1_f64
+        };
+        Self::with_address_factor(size, address_factor)
+    }
+
+    /// initializes HashTable with `size` buckets, including the cellar
+    ///
+    /// `address_factor` of the buckets are used as address region, the
+    /// rest as cellar. The address factor is clamped to (0, 1]. The table
+    /// has at least one bucket, which is always used as address region
+    pub fn with_address_factor(size: usize, address_factor: f64) -> Self {
+        let size = size.max(1);
+        let address_factor = address_factor.clamp(f64::MIN_POSITIVE, 1_f64);
         let mut entries = Vec::with_capacity(size);
         for _ in 0..size {
             entries.push(None);
         }
+        let address_buckets = ((size as f64 * address_factor) as usize).clamp(1, size);
         Self {
             collisions: 0,
+            insert_collisions: 0,
             entries,
+            address_factor,
+            address_buckets,
             hasher: PhantomData,
-            cursor: 0,
+            strategy: PhantomData,
+            cursor: size,
         }
     }
+
+    /// returns the fraction of buckets used as address region
+    pub fn address_factor(&self) -> f64 {
+        self.address_factor
+    }
+
+    /// returns the index of an empty bucket for an overflowing element
+    ///
+    /// Moves the cursor down to the next empty bucket. Buckets are never
+    /// emptied, so the table is full once the cursor reaches the start
+    fn next_free(&mut self) -> Result<usize, HashTableError> {
+        while self.cursor > 0 {
+            self.cursor -= 1;
+            if self.entries[self.cursor].is_none() {
+                return Ok(self.cursor);
+            }
+        }
+        Err(HashTableError::Full)
+    }
 }
 
-impl<T: PartialEq + Copy, H: Hasher<T>> HashTable<T> for CoalescedTable<T, H> {
+impl<T: PartialEq + Copy, H: Hasher<T>, S: CoalescingStrategy> HashTable<T>
+    for CoalescedTable<T, H, S>
+{
     /// checks table for value
     ///
-    /// checks the table through the efficient algorithm used in
-    /// separate chaining.
+    /// follows the chain starting at the bucket the value is hashed to.
+    /// Every non-empty bucket not containing the value is counted as a collision.
+    /// Does not change the table
     fn has(&mut self, val: &T) -> bool {
-        let mut index = H::hash(val, self.entries.len());
-        if self.entries[index].is_none() {
-            self.entries[index] = Some((*val, None));
-            return true;
-        }
-        self.collisions += 1;
-        loop {
-            if let Some((x, next)) = self.entries[index] {
-                if x == *val {
-                    return true;
-                }
-                self.collisions += 1;
-                if let Some(i) = next {
-                    index = i;
-                } else {
-                    break;
-                }
-            } else {
-                panic!("data inconsistency");
+        let stats = self.probe_stats(val);
+        self.collisions += stats.collisions;
+        stats.found
+    }
+    /// follows the chain starting at the home bucket, see `has`
+    fn probe_stats(&self, val: &T) -> ProbeStats {
+        let mut index = H::hash(val, self.address_buckets);
+        let mut collisions = 0;
+        while let Some((x, next)) = self.entries[index] {
+            if x == *val {
+                return ProbeStats {
+                    found: true,
+                    collisions,
+                };
+            }
+            collisions += 1;
+            match next {
+                Some(i) => index = i,
+                None => break,
+            }
+        }
+        ProbeStats {
+            found: false,
+            collisions,
+        }
+    }
+    /// follows the chain starting at the home bucket without counting collisions
+    fn contains(&self, val: &T) -> bool {
+        let mut index = H::hash(val, self.address_buckets);
+        while let Some((x, next)) = self.entries[index] {
+            if x == *val {
+                return true;
+            }
+            match next {
+                Some(i) => index = i,
+                None => break,
             }
         }
         false
@@ -72,54 +206,284 @@ impl<T: PartialEq + Copy, H: Hasher<T>> HashTable<T> for CoalescedTable<T, H> {
         self.collisions
     }
 
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
+
     /// inserts an element into the table
     ///
-    /// returns true iff the value was inserted successfully
-    /// Only fails iff the table is full and the value was
-    /// not inserted already
-    /// Insertion is not optimized for performance.
-    /// Insertion does not count collisions.
-    fn insert(&mut self, val: &T) -> bool {
-        let mut index = H::hash(val, self.entries.len());
-        if self.entries[index].is_none() {
-            self.entries[index] = Some((*val, None));
-            return true;
-        }
-        loop {
-            if let Some((x, next)) = self.entries[index] {
-                if x == *val {
-                    return true;
+    /// returns Inserted iff the value was inserted successfully
+    /// Only fails with `HashTableError::Full` iff the table is full
+    /// and the value was not inserted already
+    /// The whole chain is searched for the value first. Depending on
+    /// the strategy it is then linked after the bucket it is hashed to
+    /// or after the last bucket of the chain.
+    /// Every element of the chain checked before the value is found
+    /// or linked is counted as an insertion collision.
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
+        let home = H::hash(val, self.address_buckets);
+        let mut index = home;
+        while let Some((x, next)) = self.entries[index] {
+            if x == *val {
+                return Ok(InsertOutcome::AlreadyPresent);
+            }
+            self.insert_collisions += 1;
+            match next {
+                Some(i) => index = i,
+                None => break,
+            }
+        }
+        if self.entries[home].is_none() {
+            self.entries[home] = Some((*val, None));
+            return Ok(InsertOutcome::Inserted);
+        }
+        let free = self.next_free()?;
+        let link = if S::EARLY_INSERTION { home } else { index };
+        if let Some((x, next)) = self.entries[link] {
+            self.entries[free] = Some((*val, next));
+            self.entries[link] = Some((x, Some(free)));
+        }
+        Ok(InsertOutcome::Inserted)
+    }
+    /// resizes the number of buckets to specified byte value
+    ///
+    /// Every bucket stores an element and the index of the next bucket,
+    /// (for T = u32) a bucket has a size of 24B. If fewer buckets than
+    /// elements fit into `bytes` this method will fail. Otherwise it
+    /// resizes the hashtable to bytes / (size of a bucket) buckets.
+    /// The buckets are split between address region and cellar
+    /// according to the address factor of the table.
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let bucket_size = std::mem::size_of::<Option<(T, Option<usize>)>>();
+        let buckets = sizing::inline_buckets(bytes, elements, bucket_size)?;
+        *self = Self::with_address_factor(buckets, self.address_factor);
+        Ok(())
+    }
+    /// returns the number of buckets of the address region
+    ///
+    /// buckets of the cellar are not counted, as no element is hashed to them
+    fn buckets(&self) -> usize {
+        self.address_buckets
+    }
+    /// returns the size of the table and its buckets, including the cellar
+    fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.entries.capacity() * std::mem::size_of::<Option<(T, Option<usize>)>>()
+    }
+    /// checks that every chain ends and every element is reachable from its home
+    ///
+    /// Links have to point to occupied buckets of the table and following
+    /// them may never return to a bucket, as chains only merge. A lookup
+    /// has to reach every element from the bucket it is hashed to before
+    /// any other copy of it. Every bucket from the cursor on is occupied
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        if let Some(bucket) = (self.cursor..self.entries.len()).find(|i| self.entries[*i].is_none())
+        {
+            return Err(InvariantViolation::Layout { bucket });
+        }
+        for (bucket, entry) in self.entries.iter().enumerate() {
+            if let Some((_, Some(next))) = entry {
+                match self.entries.get(*next) {
+                    None => return Err(InvariantViolation::OutOfBounds { index: *next }),
+                    Some(None) => return Err(InvariantViolation::Layout { bucket }),
+                    Some(Some(_)) => {}
                 }
-                if let Some(i) = next {
-                    index = i;
-                } else {
-                    break;
+            }
+        }
+        // 0: not visited, 1: on the current path, 2: chain from here ends
+        let mut state = vec![0_u8; self.entries.len()];
+        for start in 0..self.entries.len() {
+            let mut index = Some(start);
+            while let Some(i) = index {
+                match state[i] {
+                    1 => return Err(InvariantViolation::Cycle { index: i }),
+                    2 => break,
+                    _ => state[i] = 1,
                 }
-            } else {
-                panic!("data inconsistency");
+                index = self.entries[i].and_then(|(_, next)| next);
+            }
+            index = Some(start);
+            while let Some(i) = index.filter(|i| state[*i] == 1) {
+                state[i] = 2;
+                index = self.entries[i].and_then(|(_, next)| next);
             }
         }
-        while self.cursor < self.entries.len() {
-            if self.entries[self.cursor].is_none() {
-                self.entries[self.cursor] = Some((*val, None));
-                let old = self.entries[index].expect("data inconsistency").0;
-                self.entries[index] = Some((old, Some(self.cursor)));
-                return true;
+        for (bucket, entry) in self.entries.iter().enumerate() {
+            if let Some((val, _)) = entry {
+                let home = H::hash(val, self.address_buckets);
+                let path = std::iter::successors(Some(home), |i| {
+                    self.entries[*i].and_then(|(_, next)| next)
+                })
+                .map(|i| (i, self.entries[i].map(|(x, _)| x)));
+                check_probe_path(bucket, val, path)?;
             }
-            self.cursor += 1;
         }
-        true
+        Ok(())
     }
-    /// resizes the number of buckets to specified byte value
-    ///
-    /// (for T = u32) Every bucket has a size of 24B. If elements*24 > bytes
-    /// this method will fail. Otherwise it resizes the hashtable to 
-    /// bytes / 24 buckets.
-    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) {
-        let entries = bytes / std::mem::size_of::<Option<(T, Option<usize>)>>();
-        if entries < elements {
-            panic!("cannot resize that low");
-        }
-        *self = Self::with_size(entries);
+}
+
+/// Builder for CoalescedTables with a custom address factor
+///
+/// Builds tables with ELEMENT_COUNT buckets, `address_factor` of them
+/// are used as address region and the rest as cellar.
+/// The address factor is kept when the table is resized.
+pub struct CoalescedTableBuilder<T: PartialEq + Copy, H: Hasher<T>, S: CoalescingStrategy> {
+    address_factor: f64,
+    // function pointers keep the builder Sync for any T
+    t: PhantomData<fn() -> T>,
+    hasher: PhantomData<fn() -> H>,
+    strategy: PhantomData<fn() -> S>,
+}
+
+impl<T: PartialEq + Copy, H: Hasher<T>, S: CoalescingStrategy> CoalescedTableBuilder<T, H, S> {
+    /// creates a builder for tables with the given address factor
+    pub fn new(address_factor: f64) -> Self {
+        Self {
+            address_factor,
+            t: PhantomData,
+            hasher: PhantomData,
+            strategy: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static + PartialEq + Copy, H: 'static + Hasher<T>, S: 'static + CoalescingStrategy>
+    HashTableBuilder<T> for CoalescedTableBuilder<T, H, S>
+{
+    /// returns a table with ELEMENT_COUNT buckets and the address factor of the builder
+    fn build(&self) -> Box<dyn HashTable<T>> {
+        Box::new(CoalescedTable::<T, H, S>::with_address_factor(
+            ELEMENT_COUNT,
+            self.address_factor,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::lookup;
+    use super::super::{ModHash, XorShiftHash};
+    use super::*;
+
+    #[test]
+    fn late_insertion_appends() {
+        let mut table = CoalescedTable::<u32, ModHash, Lisch>::with_size(8);
+        for x in &[0, 8, 16] {
+            assert_eq!(table.insert(x), Ok(InsertOutcome::Inserted));
+        }
+        // overflowing elements are stored from the end of the table
+        assert_eq!(table.entries[7].map(|(x, _)| x), Some(8));
+        assert_eq!(table.entries[6].map(|(x, _)| x), Some(16));
+        assert_eq!(lookup(&table, 0), 0);
+        assert_eq!(lookup(&table, 8), 1);
+        assert_eq!(lookup(&table, 16), 2);
+        assert_eq!(table.insert(&16), Ok(InsertOutcome::AlreadyPresent));
+    }
+
+    #[test]
+    fn early_insertion_links_after_home() {
+        let mut table = CoalescedTable::<u32, ModHash, Eisch>::with_size(8);
+        for x in &[0, 8, 16] {
+            table.insert(x).unwrap();
+        }
+        assert_eq!(lookup(&table, 0), 0);
+        assert_eq!(lookup(&table, 16), 1);
+        assert_eq!(lookup(&table, 8), 2);
+    }
+
+    #[test]
+    fn chains_coalesce() {
+        let mut table = CoalescedTable::<u32, ModHash, Lisch>::with_size(8);
+        // 8 overflows into bucket 7, so 7 is appended to the chain of 0
+        for x in &[0, 8, 7] {
+            table.insert(x).unwrap();
+        }
+        assert_eq!(lookup(&table, 7), 1);
+        // lookups hashed to bucket 0 pass 7 as well
+        assert_eq!(lookup(&table, 16), 3);
+    }
+
+    #[test]
+    fn cellar_is_used_first() {
+        let mut table = CoalescedTable::<u32, ModHash, Lich>::with_address_factor(10, 0.5);
+        assert_eq!(table.buckets(), 5);
+        assert_eq!(table.address_factor(), 0.5);
+        for x in &[0, 5, 10, 15, 20, 25] {
+            table.insert(x).unwrap();
+        }
+        // five elements overflowed, the cellar holds the first four
+        for bucket in 6..10 {
+            assert!(table.entries[bucket].is_some());
+        }
+        for x in &[0, 5, 10, 15, 20, 25] {
+            assert!(table.contains(x));
+        }
+    }
+
+    #[test]
+    fn fails_when_full() {
+        let mut table = CoalescedTable::<u32, ModHash, Eich>::with_size(4);
+        for x in 0..4 {
+            table.insert(&(x * 4)).unwrap();
+        }
+        assert_eq!(table.insert(&16), Err(HashTableError::Full));
+        assert_eq!(table.insert(&4), Ok(InsertOutcome::AlreadyPresent));
+        assert!(!table.contains(&16));
+    }
+
+    #[test]
+    fn address_factor_is_clamped_and_kept() {
+        let table = CoalescedTable::<u32, ModHash, Lich>::with_address_factor(10, 0.0);
+        assert_eq!(table.buckets(), 1);
+        let table = CoalescedTable::<u32, ModHash, Lich>::with_address_factor(10, 2.0);
+        assert_eq!(table.buckets(), 10);
+
+        let mut table = CoalescedTable::<u32, ModHash, Lich>::with_address_factor(0, 0.5);
+        assert!(!table.contains(&3));
+        assert_eq!(table.insert(&3), Ok(InsertOutcome::Inserted));
+        assert_eq!(table.insert(&4), Err(HashTableError::Full));
+        assert_eq!(table.check_invariants(), Ok(()));
+
+        let mut table = CoalescedTableBuilder::<u32, XorShiftHash, Eich>::new(0.7).build();
+        table.resize_to_bytes(1 << 16, 100).unwrap();
+        let buckets = (1 << 16) / std::mem::size_of::<Option<(u32, Option<usize>)>>();
+        assert_eq!(table.buckets(), (buckets as f64 * 0.7) as usize);
+    }
+
+    #[test]
+    fn detects_broken_links() {
+        let mut table = CoalescedTable::<u32, ModHash, Lisch>::with_size(8);
+        for x in &[0, 8, 16] {
+            table.insert(x).unwrap();
+        }
+        assert_eq!(table.check_invariants(), Ok(()));
+
+        // 0 -> 7 -> 6 -> 0
+        table.entries[6] = Some((16, Some(0)));
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Cycle { index: 0 })
+        );
+        table.entries[6] = Some((16, Some(9)));
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::OutOfBounds { index: 9 })
+        );
+        table.entries[6] = Some((16, Some(3)));
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Layout { bucket: 6 })
+        );
+        table.entries[6] = Some((16, None));
+        table.entries[7] = Some((8, None));
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Unreachable { bucket: 6 })
+        );
     }
 }
@@ -0,0 +1,466 @@
+use super::{
+    check_bucket, check_count, check_probe_path, probe_path, sizing, HashTableError, Hasher,
+    InsertOutcome, InvariantViolation, Node, NodeArena, Prober, TailInsertion, ELEMENT_COUNT, NIL,
+};
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Thread-safe HashTable as set datastructure
+///
+/// Every lookup and insertion only needs a shared reference, so one table
+/// can be used by several threads at once. The table does not count
+/// collisions itself, every call adds them to a counter owned by the
+/// calling thread instead, so threads never contend on a counter.
+/// Like HashTable it can not delete any entries or grow while in use
+pub trait ConcurrentHashTable<T>: Send + Sync {
+    /// checks if the element is in the set
+    ///
+    /// Every collision that occured during the check is added to `collisions`
+    fn has(&self, val: &T, collisions: &mut usize) -> bool;
+    /// inserts the element in the HashTable
+    ///
+    /// returns AlreadyPresent if the element is already in the HashTable,
+    /// even if another thread inserted it at the same time.
+    /// Every collision that occured before the element was inserted or
+    /// found is added to `collisions`. Fails like `HashTable::insert`
+    fn insert(&self, val: &T, collisions: &mut usize) -> Result<InsertOutcome, HashTableError>;
+    /// resize the number of buckets to most closely match the number of bytes used
+    ///
+    /// see `HashTable::resize_to_bytes`. Needs exclusive access, so it can
+    /// only be called before the table is shared
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError>;
+    /// returns the number of buckets
+    fn buckets(&self) -> usize;
+    /// returns the number of bytes used by the table
+    ///
+    /// see `HashTable::memory_usage`
+    fn memory_usage(&self) -> usize;
+    /// checks the internal invariants of the table
+    ///
+    /// see `HashTable::check_invariants`. Elements inserted by other
+    /// threads while checking may be reported as violations, so it should
+    /// only be called while no other thread uses the table
+    fn check_invariants(&self) -> Result<(), InvariantViolation>;
+}
+
+/// A generic builder for ConcurrentHashTables
+pub trait ConcurrentHashTableBuilder<T> {
+    /// returns an instance of ConcurrentHashTable
+    fn build(&self) -> Box<dyn ConcurrentHashTable<T>>;
+}
+
+/// Default implementation of ConcurrentHashTableBuilder
+///
+/// Just calls the Default trait of the inner ConcurrentHashTable type
+pub struct DefaultConcurrentHashTableBuilder<T, H: ConcurrentHashTable<T> + Default> {
+    table: PhantomData<H>,
+    t: PhantomData<T>,
+}
+
+impl<T, H: 'static + ConcurrentHashTable<T> + Default> ConcurrentHashTableBuilder<T>
+    for DefaultConcurrentHashTableBuilder<T, H>
+{
+    /// returns the default instance of ConcurrentHashTable
+    fn build(&self) -> Box<dyn ConcurrentHashTable<T>> {
+        Box::new(H::default())
+    }
+}
+
+impl<T, H: ConcurrentHashTable<T> + Default> Default for DefaultConcurrentHashTableBuilder<T, H> {
+    fn default() -> Self {
+        Self {
+            table: PhantomData,
+            t: PhantomData,
+        }
+    }
+}
+
+/// Chains of every STRIPES-th bucket, guarded by one lock
+struct Stripe<T> {
+    entries: Vec<u32>,
+    nodes: NodeArena<T>,
+}
+
+/// Lock-striped direct chaining implementation of ConcurrentHashTable
+///
+/// Bucket b belongs to stripe b % STRIPES, every stripe holds the heads
+/// of its buckets and a NodeArena for their chains behind one Mutex.
+/// Threads only block each other if they access buckets of the same
+/// stripe, lookups lock the stripe as well. With one stripe every
+/// access is serialized, like a single table behind a Mutex
+pub struct StripedChainingTable<T: PartialEq + Copy, H: Hasher<T>, const STRIPES: usize = 64> {
+    stripes: Vec<Mutex<Stripe<T>>>,
+    buckets: usize,
+    hasher: PhantomData<H>,
+}
+impl<T: PartialEq + Copy + Send, H: Hasher<T>, const STRIPES: usize> Default
+    for StripedChainingTable<T, H, STRIPES>
+{
+    /// initializes HashTable with ELEMENT_COUNT buckets
+    fn default() -> Self {
+        Self::with_size(ELEMENT_COUNT, 0)
+    }
+}
+
+impl<T: PartialEq + Copy + Send, H: Hasher<T>, const STRIPES: usize>
+    StripedChainingTable<T, H, STRIPES>
+{
+    /// initializes HashTable with `size` buckets and room for `nodes` nodes
+    ///
+    /// the nodes are split evenly between the stripes
+    fn with_size(size: usize, nodes: usize) -> Self {
+        assert!(
+            STRIPES > 0,
+            "a StripedChainingTable needs at least one stripe"
+        );
+        let stripes = (0..STRIPES)
+            .map(|s| {
+                // stripe s holds the buckets s, s + STRIPES, s + 2 * STRIPES, ...
+                let size = (size + STRIPES - 1 - s) / STRIPES;
+                let mut entries = Vec::new();
+                entries.reserve_exact(size);
+                entries.resize(size, NIL);
+                Mutex::new(Stripe {
+                    entries,
+                    nodes: NodeArena::with_capacity(nodes.div_ceil(STRIPES)),
+                })
+            })
+            .collect();
+        Self {
+            stripes,
+            buckets: size,
+            hasher: PhantomData,
+        }
+    }
+
+    /// returns the stripe of the bucket of `val` and the index of the bucket in it
+    fn locate(&self, val: &T) -> (&Mutex<Stripe<T>>, usize) {
+        let bucket = H::hash(val, self.buckets);
+        (&self.stripes[bucket % STRIPES], bucket / STRIPES)
+    }
+}
+
+impl<T: PartialEq + Copy + Send, H: Hasher<T>, const STRIPES: usize> ConcurrentHashTable<T>
+    for StripedChainingTable<T, H, STRIPES>
+where
+    H: Send + Sync,
+{
+    /// checks the chain of the bucket for value, holding the lock of its stripe
+    ///
+    /// Every node checked that did not contain the value is counted as a collision
+    fn has(&self, val: &T, collisions: &mut usize) -> bool {
+        let (stripe, index) = self.locate(val);
+        let stripe = stripe
+            .lock()
+            .expect("a thread panicked while holding a stripe");
+        stripe
+            .nodes
+            .contains_counting(stripe.entries[index], val, collisions)
+    }
+    /// inserts the element at the tail of its chain, holding the lock of its stripe
+    ///
+    /// Every node checked before the element was found or linked is counted
+    /// as a collision. Fails with `HashTableError::Full` if the arena of
+    /// the stripe can not index more nodes
+    fn insert(&self, val: &T, collisions: &mut usize) -> Result<InsertOutcome, HashTableError> {
+        let (stripe, index) = self.locate(val);
+        let mut stripe = stripe
+            .lock()
+            .expect("a thread panicked while holding a stripe");
+        let Stripe { entries, nodes } = &mut *stripe;
+        nodes.insert::<TailInsertion>(&mut entries[index], val, collisions)
+    }
+    /// resizes the number of buckets to specified byte value
+    ///
+    /// sized like a DirectChainingTable, the locks are not counted
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let node_size = size_of::<Node<T>>();
+        let bucket_size = size_of::<u32>();
+        let buckets = sizing::chained_buckets(bytes, elements, bucket_size, node_size)?;
+        *self = Self::with_size(buckets, elements);
+        Ok(())
+    }
+    /// returns the number of buckets of all stripes
+    fn buckets(&self) -> usize {
+        self.buckets
+    }
+    /// returns the size of the table, its stripes, their buckets and arenas
+    fn memory_usage(&self) -> usize {
+        let stripes: usize = self
+            .stripes
+            .iter()
+            .map(|stripe| {
+                let stripe = stripe
+                    .lock()
+                    .expect("a thread panicked while holding a stripe");
+                stripe.entries.capacity() * size_of::<u32>() + stripe.nodes.heap_size()
+            })
+            .sum();
+        size_of::<Self>() + self.stripes.capacity() * size_of::<Mutex<Stripe<T>>>() + stripes
+    }
+    /// checks the chains of every stripe like a DirectChainingTable
+    ///
+    /// Every bucket of a stripe has to belong to it. Locks one stripe at a time
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut buckets = 0;
+        for (s, stripe) in self.stripes.iter().enumerate() {
+            let stripe = stripe
+                .lock()
+                .expect("a thread panicked while holding a stripe");
+            let mut visited = vec![false; stripe.nodes.node_count()];
+            for (local, head) in stripe.entries.iter().enumerate() {
+                let bucket = local * STRIPES + s;
+                let values = stripe.nodes.chain_values(*head, &mut visited)?;
+                check_bucket(bucket, &values, |x| H::hash(x, self.buckets) == bucket)?;
+            }
+            buckets += stripe.entries.len();
+            check_count(
+                stripe.nodes.node_count(),
+                visited.iter().filter(|x| **x).count(),
+            )?;
+        }
+        check_count(self.buckets, buckets)
+    }
+}
+
+/// Value of an empty bucket of an AtomicOpenAddressingTable
+const EMPTY: u64 = 0;
+
+/// Lock-free open addressing implementation of ConcurrentHashTable for u32
+///
+/// Every bucket is an AtomicU64 holding the element + 1, or EMPTY.
+/// Elements are inserted with a compare-and-swap on the first empty
+/// bucket, if another thread claimed it first probing continues behind it.
+/// A bucket is never changed after it was claimed, so lookups only need
+/// to load the buckets. A bucket is as large as an Option<u32>
+pub struct AtomicOpenAddressingTable<P: Prober, H: Hasher<u32>> {
+    entries: Vec<AtomicU64>,
+    prober: PhantomData<P>,
+    hasher: PhantomData<H>,
+}
+impl<P: Prober, H: Hasher<u32>> Default for AtomicOpenAddressingTable<P, H> {
+    /// initializes HashTable with ELEMENT_COUNT buckets
+    fn default() -> Self {
+        Self::with_size(ELEMENT_COUNT)
+    }
+}
+
+impl<P: Prober, H: Hasher<u32>> AtomicOpenAddressingTable<P, H> {
+    /// initializes HashTable with `size` empty buckets
+    fn with_size(size: usize) -> Self {
+        let mut entries = Vec::new();
+        entries.reserve_exact(size);
+        entries.resize_with(size, || AtomicU64::new(EMPTY));
+        Self {
+            entries,
+            prober: PhantomData,
+            hasher: PhantomData,
+        }
+    }
+}
+
+impl<P: Prober, H: Hasher<u32>> ConcurrentHashTable<u32> for AtomicOpenAddressingTable<P, H>
+where
+    P: Send + Sync,
+    H: Send + Sync,
+{
+    /// probes table for value
+    ///
+    /// probes like an OpenAddressingTable. Every accessed non-empty bucket
+    /// that did not contain the value is counted as a collision
+    fn has(&self, val: &u32, collisions: &mut usize) -> bool {
+        let stored = *val as u64 + 1;
+        let mut index = H::hash(val, self.entries.len());
+        let mut attempts = 0;
+        while attempts < self.entries.len() {
+            match self.entries[index].load(Ordering::Acquire) {
+                EMPTY => return false,
+                inside if inside == stored => return true,
+                _ => {}
+            }
+            attempts += 1;
+            *collisions += 1;
+            index = (index + P::probe(attempts)) % self.entries.len();
+        }
+        false
+    }
+    /// inserts the element in the HashTable if possible
+    ///
+    /// Tries to claim the first empty bucket, unless the value is found
+    /// before reaching one. If another thread claimed the bucket in the
+    /// meantime, its value is checked like any other bucket. Every accessed
+    /// non-empty bucket that did not contain the value is counted as a
+    /// collision. If no empty bucket was reached, fails with
+    /// `HashTableError::Full` if every bucket is occupied and
+    /// `HashTableError::ProbeCycle` otherwise.
+    fn insert(&self, val: &u32, collisions: &mut usize) -> Result<InsertOutcome, HashTableError> {
+        let stored = *val as u64 + 1;
+        let mut index = H::hash(val, self.entries.len());
+        let mut attempts = 0;
+        while attempts < self.entries.len() {
+            let inside = match self.entries[index].compare_exchange(
+                EMPTY,
+                stored,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Ok(InsertOutcome::Inserted),
+                Err(inside) => inside,
+            };
+            if inside == stored {
+                return Ok(InsertOutcome::AlreadyPresent);
+            }
+            attempts += 1;
+            *collisions += 1;
+            index = (index + P::probe(attempts)) % self.entries.len();
+        }
+        if self
+            .entries
+            .iter()
+            .any(|entry| entry.load(Ordering::Acquire) == EMPTY)
+        {
+            Err(HashTableError::ProbeCycle)
+        } else {
+            Err(HashTableError::Full)
+        }
+    }
+    /// resizes the number of buckets to specified byte value
+    ///
+    /// resizes the hashtable to bytes / (size of a bucket) buckets.
+    /// A bucket has 8 bytes
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let buckets = sizing::inline_buckets(bytes, elements, size_of::<AtomicU64>())?;
+        *self = Self::with_size(buckets);
+        Ok(())
+    }
+    /// returns the number of buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table and its buckets
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.entries.capacity() * size_of::<AtomicU64>()
+    }
+    /// checks the buckets like an OpenAddressingTable
+    ///
+    /// Every bucket has to hold EMPTY or a stored u32
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for (bucket, entry) in self.entries.iter().enumerate() {
+            entries.push(match entry.load(Ordering::Acquire) {
+                EMPTY => None,
+                stored if stored <= u32::MAX as u64 + 1 => Some((stored - 1) as u32),
+                _ => return Err(InvariantViolation::Layout { bucket }),
+            });
+        }
+        for (bucket, entry) in entries.iter().enumerate() {
+            if let Some(val) = entry {
+                check_probe_path(bucket, val, probe_path::<u32, P, H>(&entries, val))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::StuckProber;
+    use super::super::{LinearProber, ModHash, XorShiftHash};
+    use super::*;
+    use std::thread;
+
+    /// inserts overlapping ranges from several threads and checks every element
+    fn shared_insertions<H: ConcurrentHashTable<u32> + Default>() {
+        let table = H::default();
+        let inserted = AtomicU64::new(0);
+        thread::scope(|scope| {
+            for t in 0..4 {
+                let (table, inserted) = (&table, &inserted);
+                scope.spawn(move || {
+                    let mut collisions = 0;
+                    // every element is inserted by two threads
+                    for x in (t * 500)..(t * 500 + 1000) {
+                        if table.insert(&x, &mut collisions) == Ok(InsertOutcome::Inserted) {
+                            inserted.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(inserted.load(Ordering::Relaxed), 2500);
+        let mut collisions = 0;
+        for x in 0..2500 {
+            assert!(table.has(&x, &mut collisions));
+        }
+        assert!(!table.has(&2500, &mut collisions));
+    }
+
+    #[test]
+    fn striped_insertions_are_not_lost() {
+        shared_insertions::<StripedChainingTable<u32, XorShiftHash>>();
+        shared_insertions::<StripedChainingTable<u32, XorShiftHash, 1>>();
+    }
+
+    #[test]
+    fn atomic_insertions_are_not_lost() {
+        shared_insertions::<AtomicOpenAddressingTable<LinearProber, XorShiftHash>>();
+    }
+
+    #[test]
+    fn counts_collisions_of_the_caller() {
+        let table = StripedChainingTable::<u32, ModHash, 4>::with_size(8, 0);
+        let mut collisions = 0;
+        for x in &[0, 8, 16] {
+            table.insert(x, &mut collisions).unwrap();
+        }
+        assert_eq!(collisions, 1 + 2);
+        let mut collisions = 0;
+        assert!(table.has(&16, &mut collisions));
+        assert_eq!(collisions, 2);
+
+        let table = AtomicOpenAddressingTable::<LinearProber, ModHash>::with_size(8);
+        let mut collisions = 0;
+        for x in &[0, 8, 16] {
+            table.insert(x, &mut collisions).unwrap();
+        }
+        assert_eq!(collisions, 1 + 2);
+        assert_eq!(
+            table.insert(&8, &mut collisions),
+            Ok(InsertOutcome::AlreadyPresent)
+        );
+    }
+
+    #[test]
+    fn atomic_table_fails_like_open_addressing() {
+        let table = AtomicOpenAddressingTable::<LinearProber, ModHash>::with_size(4);
+        let mut collisions = 0;
+        for x in 0..4 {
+            table.insert(&x, &mut collisions).unwrap();
+        }
+        assert_eq!(table.insert(&4, &mut collisions), Err(HashTableError::Full));
+
+        let table = AtomicOpenAddressingTable::<StuckProber, ModHash>::with_size(4);
+        table.insert(&0, &mut collisions).unwrap();
+        assert_eq!(
+            table.insert(&4, &mut collisions),
+            Err(HashTableError::ProbeCycle)
+        );
+        // u32::MAX is stored as u32::MAX + 1, not mistaken for an empty bucket
+        table.insert(&u32::MAX, &mut collisions).unwrap();
+        assert!(table.has(&u32::MAX, &mut collisions));
+    }
+
+    #[test]
+    fn stripes_cover_every_bucket() {
+        let mut table = StripedChainingTable::<u32, XorShiftHash, 3>::default();
+        table.resize_to_bytes(1 << 16, 1000).unwrap();
+        let buckets: usize = table
+            .stripes
+            .iter()
+            .map(|stripe| stripe.lock().unwrap().entries.len())
+            .sum();
+        assert_eq!(buckets, table.buckets());
+    }
+}
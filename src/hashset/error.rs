@@ -0,0 +1,43 @@
+use super::InfeasibleSize;
+use std::error::Error;
+use std::fmt;
+
+/// Errors of operations on a HashTable
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashTableError {
+    /// every bucket is occupied, so no element can be inserted
+    Full,
+    /// probing did not reach an empty bucket, even though the table is not full
+    ///
+    /// Happens with probers not visiting every bucket, e.g. quadratic probing
+    ProbeCycle,
+    /// the table can not be sized to the bytes requested
+    InfeasibleSize(InfeasibleSize),
+}
+
+impl fmt::Display for HashTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashTableError::Full => write!(f, "the table is full"),
+            HashTableError::ProbeCycle => {
+                write!(f, "probing did not reach an empty bucket")
+            }
+            HashTableError::InfeasibleSize(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for HashTableError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HashTableError::InfeasibleSize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<InfeasibleSize> for HashTableError {
+    fn from(e: InfeasibleSize) -> Self {
+        HashTableError::InfeasibleSize(e)
+    }
+}
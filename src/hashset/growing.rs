@@ -0,0 +1,922 @@
+use super::{
+    check_bucket, check_count, check_probe_path, probe_path, sizing, HashTable, HashTableError,
+    Hasher, InsertOutcome, InvariantViolation, Node, NodeArena, ProbeStats, Prober, TailInsertion,
+    NIL,
+};
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+/// Number of buckets growing tables start with
+pub const INITIAL_BUCKETS: usize = 16;
+/// Average number of elements per bucket above which growing tables grow
+pub const MAX_LOAD_FACTOR: f64 = 1.0;
+/// Fraction of occupied buckets above which growing open addressing tables grow
+pub const MAX_OPEN_LOAD_FACTOR: f64 = 0.5;
+/// Number of times a growing open addressing table doubles when it rehashes
+///
+/// The table rehashes only if an element can not be moved to the new
+/// buckets, see `GrowingOpenAddressingTable::rehash`
+const MAX_REHASH_DOUBLINGS: u32 = 3;
+/// Range of the full hash values used to address growing tables
+///
+/// Growing tables hash every element once to [0, HASH_RANGE) and reduce
+/// the hash to the current number of buckets, so elements can be moved
+/// without knowing the size the table had when they were inserted
+const HASH_RANGE: usize = u32::MAX as usize + 1;
+
+/// Incrementally growing chaining table using linear hashing
+///
+/// The table starts with n0 buckets and splits one bucket whenever an
+/// insertion pushes the load factor above MAX_LOAD_FACTOR. Buckets are
+/// split in order, marked by the split pointer p. At level L an element
+/// with the hash h is stored in bucket h mod n0*2^L, or in bucket
+/// h mod n0*2^(L+1) if that bucket was split already. Once every bucket
+/// of a level is split the level increases and p starts over.
+/// Every insertion moves the elements of at most one bucket, so there is
+/// no full rehash. Only the bucket array and the arena are reallocated
+/// when they grow, which copies them but does not hash any element.
+pub struct LinearHashingTable<T: PartialEq + Copy, H: Hasher<T>> {
+    collisions: usize,
+    insert_collisions: usize,
+    entries: Vec<u32>,
+    nodes: NodeArena<T>,
+    elements: usize,
+    initial_buckets: usize,
+    level: u32,
+    split: usize,
+    hasher: PhantomData<H>,
+}
+impl<T: PartialEq + Copy, H: Hasher<T>> Default for LinearHashingTable<T, H> {
+    /// initializes HashTable with INITIAL_BUCKETS buckets
+    fn default() -> Self {
+        Self::with_size(INITIAL_BUCKETS, 0)
+    }
+}
+
+impl<T: PartialEq + Copy, H: Hasher<T>> LinearHashingTable<T, H> {
+    /// initializes HashTable with `size` buckets and room for `nodes` nodes
+    fn with_size(size: usize, nodes: usize) -> Self {
+        let size = size.max(1);
+        let mut entries = Vec::new();
+        entries.reserve_exact(size);
+        entries.resize(size, NIL);
+        Self {
+            collisions: 0,
+            insert_collisions: 0,
+            entries,
+            nodes: NodeArena::with_capacity(nodes),
+            elements: 0,
+            initial_buckets: size,
+            level: 0,
+            split: 0,
+            hasher: PhantomData,
+        }
+    }
+
+    /// returns the number of buckets of the current level before splitting
+    fn level_buckets(&self) -> usize {
+        self.initial_buckets << self.level
+    }
+
+    /// returns the bucket of an element with the hash `hash`
+    fn address(&self, hash: usize) -> usize {
+        let index = hash % self.level_buckets();
+        if index < self.split {
+            hash % (self.level_buckets() << 1)
+        } else {
+            index
+        }
+    }
+
+    /// splits the bucket at the split pointer into itself and a new bucket
+    ///
+    /// The order of the elements in both chains is kept.
+    /// Moving elements is not counted as collisions
+    fn split_bucket(&mut self) {
+        let old = self.split;
+        let new = old + self.level_buckets();
+        let modulus = self.level_buckets() << 1;
+        self.entries.push(NIL);
+
+        let mut index = self.entries[old];
+        self.entries[old] = NIL;
+        let mut tails = [NIL, NIL];
+        while index != NIL {
+            let next = self.nodes.get(index).next;
+            self.nodes.get_mut(index).next = NIL;
+            let target = if H::hash(&self.nodes.get(index).value, HASH_RANGE) % modulus == old {
+                0
+            } else {
+                1
+            };
+            if tails[target] == NIL {
+                self.entries[[old, new][target]] = index;
+            } else {
+                self.nodes.get_mut(tails[target]).next = index;
+            }
+            tails[target] = index;
+            index = next;
+        }
+
+        self.split += 1;
+        if self.split == self.level_buckets() {
+            self.level += 1;
+            self.split = 0;
+        }
+    }
+}
+
+impl<T: PartialEq + Copy, H: Hasher<T>> HashTable<T> for LinearHashingTable<T, H> {
+    /// checks table for value
+    ///
+    /// checks by following the chain at the correct bucket.
+    /// Counts the number of collisions
+    fn has(&mut self, val: &T) -> bool {
+        let stats = self.probe_stats(val);
+        self.collisions += stats.collisions;
+        stats.found
+    }
+    /// checks the chain of the addressed bucket for value, see `has`
+    fn probe_stats(&self, val: &T) -> ProbeStats {
+        let index = self.address(H::hash(val, HASH_RANGE));
+        let mut collisions = 0;
+        let found = self
+            .nodes
+            .contains_counting(self.entries[index], val, &mut collisions);
+        ProbeStats { found, collisions }
+    }
+    /// checks the chain of the addressed bucket for value without counting collisions
+    fn contains(&self, val: &T) -> bool {
+        let index = self.address(H::hash(val, HASH_RANGE));
+        self.nodes.contains(self.entries[index], val)
+    }
+    /// resets number of collisions
+    fn reset_collisions(&mut self) {
+        self.collisions = 0;
+    }
+    /// returns number of collisions
+    fn get_collisions(&self) -> usize {
+        self.collisions
+    }
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
+    /// inserts the element into the HashTable
+    ///
+    /// only fails with `HashTableError::Full` if the arena can not index
+    /// another node. Every node of the chain checked before the value is
+    /// found or linked is counted as an insertion collision.
+    /// Splits one bucket if the load factor exceeds MAX_LOAD_FACTOR afterwards
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
+        let index = self.address(H::hash(val, HASH_RANGE));
+        let outcome = self.nodes.insert::<TailInsertion>(
+            &mut self.entries[index],
+            val,
+            &mut self.insert_collisions,
+        )?;
+        if outcome == InsertOutcome::Inserted {
+            self.elements += 1;
+            if self.elements as f64 > MAX_LOAD_FACTOR * self.entries.len() as f64 {
+                self.split_bucket();
+            }
+        }
+        Ok(outcome)
+    }
+    /// resizes the number of buckets to specified byte value
+    ///
+    /// sized like a DirectChainingTable, the number of buckets found is
+    /// the initial number of buckets n0. The table only grows beyond
+    /// `bytes` if `elements` exceed MAX_LOAD_FACTOR per bucket.
+    /// Fails if not even one bucket fits into the bytes left for buckets
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let node_size = size_of::<Node<T>>();
+        let bucket_size = size_of::<u32>();
+        let buckets = sizing::chained_buckets(bytes, elements, bucket_size, node_size)?;
+        *self = Self::with_size(buckets, elements);
+        Ok(())
+    }
+    /// returns the number of buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table, its buckets and the arena
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.entries.capacity() * size_of::<u32>() + self.nodes.heap_size()
+    }
+    /// checks the chains like a DirectChainingTable
+    ///
+    /// Every element has to be in the bucket it is addressed to at the
+    /// current level and split pointer. The buckets have to be the buckets
+    /// of the level plus the split ones, the elements counted the nodes stored
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        check_count(self.level_buckets() + self.split, self.entries.len())?;
+        let mut visited = vec![false; self.nodes.node_count()];
+        for (bucket, head) in self.entries.iter().enumerate() {
+            let values = self.nodes.chain_values(*head, &mut visited)?;
+            check_bucket(bucket, &values, |x| {
+                self.address(H::hash(x, HASH_RANGE)) == bucket
+            })?;
+        }
+        check_count(self.elements, self.nodes.node_count())?;
+        check_count(
+            self.nodes.node_count(),
+            visited.iter().filter(|x| **x).count(),
+        )
+    }
+}
+
+/// Chaining table growing by stop-the-world rehashing
+///
+/// Once an insertion pushes the load factor above MAX_LOAD_FACTOR the
+/// number of buckets is doubled and every element is moved into a new
+/// bucket array and arena at once. Used as the comparison for
+/// incrementally growing tables.
+pub struct RehashingChainingTable<T: PartialEq + Copy, H: Hasher<T>> {
+    collisions: usize,
+    insert_collisions: usize,
+    entries: Vec<u32>,
+    nodes: NodeArena<T>,
+    elements: usize,
+    hasher: PhantomData<H>,
+}
+impl<T: PartialEq + Copy, H: Hasher<T>> Default for RehashingChainingTable<T, H> {
+    /// initializes HashTable with INITIAL_BUCKETS buckets
+    fn default() -> Self {
+        Self::with_size(INITIAL_BUCKETS, 0)
+    }
+}
+
+impl<T: PartialEq + Copy, H: Hasher<T>> RehashingChainingTable<T, H> {
+    /// initializes HashTable with `size` buckets and room for `nodes` nodes
+    fn with_size(size: usize, nodes: usize) -> Self {
+        let size = size.max(1);
+        let mut entries = Vec::new();
+        entries.reserve_exact(size);
+        entries.resize(size, NIL);
+        Self {
+            collisions: 0,
+            insert_collisions: 0,
+            entries,
+            nodes: NodeArena::with_capacity(nodes),
+            elements: 0,
+            hasher: PhantomData,
+        }
+    }
+
+    /// moves every element into a table with twice the buckets
+    ///
+    /// Moving elements is not counted as collisions
+    fn rehash(&mut self) -> Result<(), HashTableError> {
+        let mut grown = Self::with_size(self.entries.len() << 1, self.elements);
+        let mut uncounted = 0;
+        for head in &self.entries {
+            let mut index = *head;
+            while index != NIL {
+                let node = *self.nodes.get(index);
+                let bucket = H::hash(&node.value, grown.entries.len());
+                grown.nodes.insert::<TailInsertion>(
+                    &mut grown.entries[bucket],
+                    &node.value,
+                    &mut uncounted,
+                )?;
+                index = node.next;
+            }
+        }
+        self.entries = grown.entries;
+        self.nodes = grown.nodes;
+        Ok(())
+    }
+}
+
+impl<T: PartialEq + Copy, H: Hasher<T>> HashTable<T> for RehashingChainingTable<T, H> {
+    /// checks table for value
+    ///
+    /// checks by following the chain at the correct bucket.
+    /// Counts the number of collisions
+    fn has(&mut self, val: &T) -> bool {
+        let stats = self.probe_stats(val);
+        self.collisions += stats.collisions;
+        stats.found
+    }
+    /// checks the chain of the bucket for value, see `has`
+    fn probe_stats(&self, val: &T) -> ProbeStats {
+        let index = H::hash(val, self.entries.len());
+        let mut collisions = 0;
+        let found = self
+            .nodes
+            .contains_counting(self.entries[index], val, &mut collisions);
+        ProbeStats { found, collisions }
+    }
+    /// checks the chain of the bucket for value without counting collisions
+    fn contains(&self, val: &T) -> bool {
+        let index = H::hash(val, self.entries.len());
+        self.nodes.contains(self.entries[index], val)
+    }
+    /// resets number of collisions
+    fn reset_collisions(&mut self) {
+        self.collisions = 0;
+    }
+    /// returns number of collisions
+    fn get_collisions(&self) -> usize {
+        self.collisions
+    }
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
+    /// inserts the element into the HashTable
+    ///
+    /// Rehashes the whole table first if the value is new and would push
+    /// the load factor above MAX_LOAD_FACTOR. Only fails with
+    /// `HashTableError::Full` if the arena can not index another node, the
+    /// value is not inserted then. Every node of the chain checked before
+    /// the value is found or linked is counted as an insertion collision
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
+        if (self.elements + 1) as f64 > MAX_LOAD_FACTOR * self.entries.len() as f64
+            && !self.contains(val)
+        {
+            self.rehash()?;
+        }
+        let index = H::hash(val, self.entries.len());
+        let outcome = self.nodes.insert::<TailInsertion>(
+            &mut self.entries[index],
+            val,
+            &mut self.insert_collisions,
+        )?;
+        if outcome == InsertOutcome::Inserted {
+            self.elements += 1;
+        }
+        Ok(outcome)
+    }
+    /// resizes the number of buckets to specified byte value
+    ///
+    /// sized like a DirectChainingTable. The table only grows beyond
+    /// `bytes` if `elements` exceed MAX_LOAD_FACTOR per bucket.
+    /// Fails if not even one bucket fits into the bytes left for buckets
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let node_size = size_of::<Node<T>>();
+        let bucket_size = size_of::<u32>();
+        let buckets = sizing::chained_buckets(bytes, elements, bucket_size, node_size)?;
+        *self = Self::with_size(buckets, elements);
+        Ok(())
+    }
+    /// returns the number of buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table, its buckets and the arena
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.entries.capacity() * size_of::<u32>() + self.nodes.heap_size()
+    }
+    /// checks the chains like a DirectChainingTable
+    ///
+    /// The elements counted have to be the nodes stored
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut visited = vec![false; self.nodes.node_count()];
+        for (bucket, head) in self.entries.iter().enumerate() {
+            let values = self.nodes.chain_values(*head, &mut visited)?;
+            check_bucket(bucket, &values, |x| {
+                H::hash(x, self.entries.len()) == bucket
+            })?;
+        }
+        check_count(self.elements, self.nodes.node_count())?;
+        check_count(
+            self.nodes.node_count(),
+            visited.iter().filter(|x| **x).count(),
+        )
+    }
+}
+
+/// How the elements of a growing open addressing table are moved to the new buckets
+pub trait Migration {
+    /// Number of old buckets moved on every insertion, usize::MAX moves all at once
+    const BUCKETS_PER_OPERATION: usize;
+}
+
+/// Every element is moved as soon as the table grows
+pub struct StopTheWorld;
+impl Migration for StopTheWorld {
+    const BUCKETS_PER_OPERATION: usize = usize::MAX;
+}
+
+/// A few old buckets are moved on every insertion
+///
+/// Moving 4 buckets per insertion finishes long before the table has to
+/// grow again, as it grows once more than half of its buckets are occupied
+pub struct Incremental;
+impl Migration for Incremental {
+    const BUCKETS_PER_OPERATION: usize = 4;
+}
+
+/// Result of probing an array of buckets for an element
+enum Probe {
+    /// the element is stored in the bucket
+    Found,
+    /// the element is not stored, the bucket is the first empty one
+    Empty(usize),
+    /// every attempt reached an occupied bucket without the element
+    Exhausted,
+}
+
+/// probes `entries` for `val` like an OpenAddressingTable
+///
+/// Every accessed non-empty bucket not containing the value is added to `collisions`
+fn probe<T: PartialEq + Copy, P: Prober, H: Hasher<T>>(
+    entries: &[Option<T>],
+    val: &T,
+    collisions: &mut usize,
+) -> Probe {
+    if entries.is_empty() {
+        return Probe::Exhausted;
+    }
+    let mut index = H::hash(val, entries.len());
+    let mut attempts = 0;
+    while attempts < entries.len() {
+        match entries[index] {
+            Some(inside) if inside == *val => return Probe::Found,
+            Some(_) => {}
+            None => return Probe::Empty(index),
+        }
+        attempts += 1;
+        *collisions += 1;
+        index = (index + P::probe(attempts)) % entries.len();
+    }
+    Probe::Exhausted
+}
+
+/// probes `entries` for `val` like `probe`, without counting collisions
+fn contains<T: PartialEq + Copy, P: Prober, H: Hasher<T>>(entries: &[Option<T>], val: &T) -> bool {
+    if entries.is_empty() {
+        return false;
+    }
+    let mut index = H::hash(val, entries.len());
+    let mut attempts = 0;
+    while attempts < entries.len() {
+        match entries[index] {
+            Some(inside) if inside == *val => return true,
+            Some(_) => {}
+            None => return false,
+        }
+        attempts += 1;
+        index = (index + P::probe(attempts)) % entries.len();
+    }
+    false
+}
+
+/// Growing HashTable with OpenAddressing
+///
+/// Once an insertion makes more than MAX_OPEN_LOAD_FACTOR of the buckets
+/// occupied the number of buckets is doubled. The old buckets are kept
+/// until every element has been moved, `M` decides how many are moved on
+/// every insertion. While elements are moved lookups probe the new buckets
+/// first and the old buckets afterwards, collisions in both are counted.
+/// Old buckets are never cleared while moving, so every probe sequence
+/// in them stays intact. If an element can not be moved, every element
+/// is rehashed into more buckets at once. Lookups never move elements.
+pub struct GrowingOpenAddressingTable<
+    T: PartialEq + Copy,
+    P: Prober,
+    H: Hasher<T>,
+    M: Migration = Incremental,
+> {
+    collisions: usize,
+    insert_collisions: usize,
+    entries: Vec<Option<T>>,
+    old: Vec<Option<T>>,
+    migrated: usize,
+    elements: usize,
+    prober: PhantomData<P>,
+    hasher: PhantomData<H>,
+    migration: PhantomData<M>,
+}
+impl<T: PartialEq + Copy, P: Prober, H: Hasher<T>, M: Migration> Default
+    for GrowingOpenAddressingTable<T, P, H, M>
+{
+    /// initializes HashTable with INITIAL_BUCKETS buckets
+    fn default() -> Self {
+        Self::with_size(INITIAL_BUCKETS)
+    }
+}
+
+impl<T: PartialEq + Copy, P: Prober, H: Hasher<T>, M: Migration>
+    GrowingOpenAddressingTable<T, P, H, M>
+{
+    /// initializes HashTable with `size` buckets
+    fn with_size(size: usize) -> Self {
+        Self {
+            collisions: 0,
+            insert_collisions: 0,
+            entries: Self::empty_buckets(size.max(1)),
+            old: Vec::new(),
+            migrated: 0,
+            elements: 0,
+            prober: PhantomData,
+            hasher: PhantomData,
+            migration: PhantomData,
+        }
+    }
+
+    /// allocates exactly `size` empty buckets
+    fn empty_buckets(size: usize) -> Vec<Option<T>> {
+        let mut entries = Vec::new();
+        entries.reserve_exact(size);
+        entries.resize(size, None);
+        entries
+    }
+
+    /// moves the elements of up to `buckets` old buckets to the new buckets
+    ///
+    /// Frees the old buckets once every element is moved.
+    /// Moving elements is not counted as collisions. Every bucket counts as
+    /// moved as soon as its element is. If an element can not be moved the
+    /// table is rehashed, so it does not block every later insertion
+    fn migrate(&mut self, buckets: usize) -> Result<(), HashTableError> {
+        let end = self.old.len().min(self.migrated.saturating_add(buckets));
+        let mut uncounted = 0;
+        while self.migrated < end {
+            if let Some(val) = self.old[self.migrated] {
+                match probe::<T, P, H>(&self.entries, &val, &mut uncounted) {
+                    Probe::Empty(i) => self.entries[i] = Some(val),
+                    Probe::Found => {}
+                    Probe::Exhausted => return self.rehash(),
+                }
+            }
+            self.migrated += 1;
+        }
+        if self.migrated == self.old.len() {
+            self.old = Vec::new();
+            self.migrated = 0;
+        }
+        Ok(())
+    }
+
+    /// moves every element at once into more new buckets
+    ///
+    /// Doubles the number of new buckets until the elements of the new
+    /// buckets and of the old buckets not moved yet all fit, at most
+    /// MAX_REHASH_DOUBLINGS times, and frees the old buckets. Moving is not
+    /// counted as collisions. Fails with `HashTableError::ProbeCycle` if
+    /// they still do not fit, the table is not changed then
+    fn rehash(&mut self) -> Result<(), HashTableError> {
+        let elements: Vec<T> = self
+            .entries
+            .iter()
+            .chain(&self.old[self.migrated..])
+            .flatten()
+            .copied()
+            .collect();
+        let mut size = self.entries.len();
+        'sizes: for _ in 0..MAX_REHASH_DOUBLINGS {
+            size <<= 1;
+            let mut grown = Self::empty_buckets(size);
+            for val in &elements {
+                match probe::<T, P, H>(&grown, val, &mut 0) {
+                    Probe::Empty(index) => grown[index] = Some(*val),
+                    _ => continue 'sizes,
+                }
+            }
+            self.entries = grown;
+            self.old = Vec::new();
+            self.migrated = 0;
+            return Ok(());
+        }
+        Err(HashTableError::ProbeCycle)
+    }
+
+    /// doubles the number of buckets and starts moving the elements
+    ///
+    /// An unfinished migration is finished first
+    fn grow(&mut self) -> Result<(), HashTableError> {
+        self.migrate(usize::MAX)?;
+        let grown = Self::empty_buckets(self.entries.len() << 1);
+        self.old = std::mem::replace(&mut self.entries, grown);
+        self.migrated = 0;
+        self.migrate(M::BUCKETS_PER_OPERATION)
+    }
+}
+
+impl<T: PartialEq + Copy, P: Prober, H: Hasher<T>, M: Migration> HashTable<T>
+    for GrowingOpenAddressingTable<T, P, H, M>
+{
+    /// probes table for value
+    ///
+    /// probes the new buckets and, while elements are moved, the old buckets.
+    /// Every accessed non-empty bucket that did not contain the value
+    /// searched for is counted as a collision, in both arrays
+    fn has(&mut self, val: &T) -> bool {
+        let stats = self.probe_stats(val);
+        self.collisions += stats.collisions;
+        stats.found
+    }
+    /// probes the new and the old buckets for value, see `has`
+    fn probe_stats(&self, val: &T) -> ProbeStats {
+        let mut collisions = 0;
+        let found = matches!(
+            probe::<T, P, H>(&self.entries, val, &mut collisions),
+            Probe::Found
+        ) || matches!(
+            probe::<T, P, H>(&self.old, val, &mut collisions),
+            Probe::Found
+        );
+        ProbeStats { found, collisions }
+    }
+    /// probes the new and the old buckets for value without counting collisions
+    fn contains(&self, val: &T) -> bool {
+        contains::<T, P, H>(&self.entries, val) || contains::<T, P, H>(&self.old, val)
+    }
+    /// resets number of collisions
+    fn reset_collisions(&mut self) {
+        self.collisions = 0;
+    }
+    /// returns number of collisions
+    fn get_collisions(&self) -> usize {
+        self.collisions
+    }
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
+    /// inserts the element in the HashTable if possible
+    ///
+    /// Probes the new buckets and the old buckets for the value. Every
+    /// accessed non-empty bucket that did not contain the value is counted
+    /// as an insertion collision. Unless it was found in either, old buckets
+    /// are moved first and the table grows if the value would make more
+    /// than MAX_OPEN_LOAD_FACTOR of the buckets occupied. Then the value is
+    /// put into the first empty new bucket, probing again without counting
+    /// if elements were moved. Fails with `HashTableError::ProbeCycle` if an
+    /// element could neither be moved nor rehashed or no empty bucket was
+    /// reached, the value is not inserted then.
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
+        let mut empty = match probe::<T, P, H>(&self.entries, val, &mut self.insert_collisions) {
+            Probe::Found => return Ok(InsertOutcome::AlreadyPresent),
+            Probe::Empty(index) => Some(index),
+            Probe::Exhausted => None,
+        };
+        if let Probe::Found = probe::<T, P, H>(&self.old, val, &mut self.insert_collisions) {
+            return Ok(InsertOutcome::AlreadyPresent);
+        }
+        if !self.old.is_empty() {
+            self.migrate(M::BUCKETS_PER_OPERATION)?;
+            empty = None;
+        }
+        if (self.elements + 1) as f64 > MAX_OPEN_LOAD_FACTOR * self.entries.len() as f64 {
+            self.grow()?;
+            empty = None;
+        }
+        let empty = match empty {
+            Some(index) => index,
+            None => match probe::<T, P, H>(&self.entries, val, &mut 0) {
+                Probe::Empty(index) => index,
+                _ => return Err(HashTableError::ProbeCycle),
+            },
+        };
+        self.entries[empty] = Some(*val);
+        self.elements += 1;
+        Ok(InsertOutcome::Inserted)
+    }
+    /// resizes the number of buckets to specified byte value
+    ///
+    /// resizes the hashtable to bytes / (size of a bucket) buckets.
+    /// (for T = u32) a bucket has 8 bytes. The table only grows beyond
+    /// `bytes` if more than MAX_OPEN_LOAD_FACTOR of the buckets are occupied.
+    /// Fails if fewer buckets than elements fit into `bytes`
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let buckets = sizing::inline_buckets(bytes, elements, size_of::<Option<T>>())?;
+        *self = Self::with_size(buckets);
+        Ok(())
+    }
+    /// returns the number of new buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table, its new and its old buckets
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>() + (self.entries.capacity() + self.old.capacity()) * size_of::<Option<T>>()
+    }
+    /// checks that every element is stored once and reachable by a lookup
+    ///
+    /// Elements in the new buckets have to be reachable there, elements
+    /// in old buckets not moved yet have to be reachable in the old buckets
+    /// and may not be stored in the new buckets. Moved old buckets are
+    /// only kept to keep probe sequences intact, they are not checked.
+    /// The elements counted have to be the elements stored
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        if self.migrated > self.old.len() {
+            return Err(InvariantViolation::OutOfBounds {
+                index: self.migrated,
+            });
+        }
+        for (bucket, entry) in self.entries.iter().enumerate() {
+            if let Some(val) = entry {
+                check_probe_path(bucket, val, probe_path::<T, P, H>(&self.entries, val))?;
+            }
+        }
+        let mut stored = self.entries.iter().flatten().count();
+        for (bucket, entry) in self.old.iter().enumerate().skip(self.migrated) {
+            if let Some(val) = entry {
+                check_probe_path(bucket, val, probe_path::<T, P, H>(&self.old, val))?;
+                if !matches!(
+                    probe::<T, P, H>(&self.entries, val, &mut 0),
+                    Probe::Empty(_)
+                ) {
+                    return Err(InvariantViolation::Duplicate { bucket });
+                }
+                stored += 1;
+            }
+        }
+        check_count(self.elements, stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::StuckProber;
+    use super::super::{LinearProber, ModHash, QuadraticProber, XorShiftHash};
+    use super::*;
+
+    /// inserts 0..n and checks that every element stays in the table
+    fn grows_without_losing_elements<H: HashTable<u32> + Default>(n: u32) -> H {
+        let mut table = H::default();
+        let initial = table.buckets();
+        for x in 0..n {
+            assert_eq!(table.insert(&x), Ok(InsertOutcome::Inserted));
+            assert_eq!(table.insert(&x), Ok(InsertOutcome::AlreadyPresent));
+        }
+        assert!(table.buckets() > initial);
+        for x in 0..n {
+            assert!(table.contains(&x), "lost {}", x);
+        }
+        assert!(!table.contains(&n));
+        table
+    }
+
+    #[test]
+    fn linear_hashing_splits_one_bucket_at_a_time() {
+        let table = grows_without_losing_elements::<LinearHashingTable<u32, XorShiftHash>>(5000);
+        assert!(table.buckets() as f64 * MAX_LOAD_FACTOR >= 5000_f64);
+        // splitting only ever appends one bucket
+        assert_eq!(
+            table.buckets(),
+            table.level_buckets() + table.split,
+            "buckets of the level and the split ones"
+        );
+    }
+
+    #[test]
+    fn linear_hashing_addresses_split_buckets() {
+        let mut table = LinearHashingTable::<u32, ModHash>::with_size(4, 0);
+        for x in 0..5 {
+            table.insert(&x).unwrap();
+        }
+        // bucket 0 was split, 4 moved to bucket 4
+        assert_eq!(table.buckets(), 5);
+        assert_eq!(table.address(4), 4);
+        assert_eq!(table.address(8), 0);
+        assert_eq!(table.address(5), 1);
+    }
+
+    #[test]
+    fn rehashing_doubles() {
+        let table =
+            grows_without_losing_elements::<RehashingChainingTable<u32, XorShiftHash>>(5000);
+        assert!(table.buckets().is_power_of_two());
+        assert!(table.buckets() as f64 * MAX_LOAD_FACTOR >= 5000_f64);
+
+        // the table grows before a new value is linked, but not for duplicates
+        let mut table = RehashingChainingTable::<u32, ModHash>::with_size(4, 0);
+        for x in 0..4 {
+            table.insert(&x).unwrap();
+        }
+        assert_eq!(table.insert(&0), Ok(InsertOutcome::AlreadyPresent));
+        assert_eq!(table.buckets(), 4);
+        assert_eq!(table.insert(&4), Ok(InsertOutcome::Inserted));
+        assert_eq!(table.buckets(), 8);
+        assert_eq!(table.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn open_addressing_grows_incrementally() {
+        let table = grows_without_losing_elements::<
+            GrowingOpenAddressingTable<u32, LinearProber, XorShiftHash>,
+        >(5000);
+        assert!(table.entries.len() as f64 * MAX_OPEN_LOAD_FACTOR >= 5000_f64);
+
+        let mut table = GrowingOpenAddressingTable::<u32, LinearProber, XorShiftHash>::default();
+        let initial = table.buckets();
+        for x in 0..=(initial as f64 * MAX_OPEN_LOAD_FACTOR) as u32 {
+            table.insert(&x).unwrap();
+        }
+        // the old buckets are still being moved
+        assert_eq!(table.buckets(), initial << 1);
+        assert!(!table.old.is_empty());
+        for x in 0..=(initial as f64 * MAX_OPEN_LOAD_FACTOR) as u32 {
+            assert!(table.contains(&x));
+        }
+    }
+
+    #[test]
+    fn open_addressing_stops_the_world() {
+        grows_without_losing_elements::<
+            GrowingOpenAddressingTable<u32, QuadraticProber, XorShiftHash, StopTheWorld>,
+        >(5000);
+        let mut table =
+            GrowingOpenAddressingTable::<u32, LinearProber, XorShiftHash, StopTheWorld>::default();
+        for x in 0..1000 {
+            table.insert(&x).unwrap();
+            assert!(table.old.is_empty());
+        }
+    }
+
+    #[test]
+    fn failed_insertions_are_not_stored() {
+        // every value hashed to an occupied bucket fails, also while the table grows
+        let mut table = GrowingOpenAddressingTable::<u32, StuckProber, ModHash>::with_size(8);
+        let mut failures = 0;
+        for x in 1..200 {
+            for val in &[x, x << 10] {
+                match table.insert(val) {
+                    Ok(outcome) => assert_eq!(outcome, InsertOutcome::Inserted),
+                    Err(e) => {
+                        assert_eq!(e, HashTableError::ProbeCycle);
+                        assert!(!table.contains(val));
+                        failures += 1;
+                    }
+                }
+                assert_eq!(table.check_invariants(), Ok(()));
+            }
+        }
+        assert!(failures > 0);
+        assert!(table.buckets() > 8);
+    }
+
+    #[test]
+    fn failed_migrations_rehash() {
+        let mut table = GrowingOpenAddressingTable::<u32, StuckProber, ModHash>::with_size(8);
+        for x in &[7, 0, 1, 2] {
+            table.insert(x).unwrap();
+        }
+        // grows to 16 buckets and moves the old buckets 0 to 3
+        table.insert(&23).unwrap();
+        assert_eq!(table.migrated, 4);
+        // 7 can not be moved to bucket 7, 23 is stored there
+        assert_eq!(table.insert(&3), Ok(InsertOutcome::Inserted));
+        assert!(table.old.is_empty());
+        assert_eq!(table.buckets(), 32);
+        assert_eq!(table.check_invariants(), Ok(()));
+        for x in &[7, 0, 1, 2, 23, 3] {
+            assert!(table.contains(x), "lost {}", x);
+        }
+        assert_eq!(table.insert(&4), Ok(InsertOutcome::Inserted));
+    }
+
+    #[test]
+    fn migration_is_not_counted() {
+        let mut table = LinearHashingTable::<u32, XorShiftHash>::default();
+        let mut expected = 0;
+        for x in 0..5000 {
+            let before = table.probe_stats(&x).collisions;
+            table.insert(&x).unwrap();
+            expected += before;
+        }
+        assert_eq!(table.get_insert_collisions(), expected);
+    }
+
+    #[test]
+    fn detects_wrong_counts() {
+        let mut table = LinearHashingTable::<u32, XorShiftHash>::default();
+        for x in 0..100 {
+            table.insert(&x).unwrap();
+        }
+        assert_eq!(table.check_invariants(), Ok(()));
+        table.split += 1;
+        assert!(table.check_invariants().is_err());
+
+        let mut table = GrowingOpenAddressingTable::<u32, LinearProber, XorShiftHash>::default();
+        for x in 0..100 {
+            table.insert(&x).unwrap();
+            assert_eq!(table.check_invariants(), Ok(()));
+        }
+        table.elements += 1;
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Count {
+                expected: 101,
+                found: 100
+            })
+        );
+    }
+}
@@ -46,6 +46,65 @@ pub trait Hasher<T> {
     ///
     /// - val: A reference to the value to hash
     /// - max: The length of the hashset
+    ///
     /// returns: An integer value in the interval [0, max)
     fn hash(val: &T, max: usize) -> usize;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// hashes of every hasher for `val` into `max` buckets
+    fn hashes(val: u32, max: usize) -> [usize; 3] {
+        [
+            ModHash::hash(&val, max),
+            MulHash::hash(&val, max),
+            XorShiftHash::hash(&val, max),
+        ]
+    }
+
+    #[test]
+    fn hashes_are_in_range() {
+        for &max in &[1, 2, 7, 16, 1000, 1 << 15] {
+            for val in (0..1000).chain(u32::MAX - 1000..=u32::MAX) {
+                for hash in hashes(val, max).iter() {
+                    assert!(*hash < max, "hash {} of {} not below {}", hash, val, max);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hashes_are_deterministic() {
+        for val in 0..1000 {
+            assert_eq!(hashes(val, 1 << 15), hashes(val, 1 << 15));
+        }
+    }
+
+    #[test]
+    fn mod_hash_is_modulo() {
+        assert_eq!(ModHash::hash(&17, 16), 1);
+        assert_eq!(ModHash::hash(&16, 16), 0);
+        assert_eq!(ModHash::hash(&u32::MAX, 1 << 15), (1 << 15) - 1);
+    }
+
+    #[test]
+    fn hashes_spread_consecutive_values() {
+        // consecutive values should hit most of the buckets
+        let max = 64;
+        for hasher in 0..3 {
+            let mut hit = [false; 64];
+            for val in 0..(max as u32 * 4) {
+                hit[hashes(val, max)[hasher]] = true;
+            }
+            let used = hit.iter().filter(|x| **x).count();
+            assert!(
+                used > max * 3 / 4,
+                "hasher {} only used {} buckets",
+                hasher,
+                used
+            );
+        }
+    }
+}
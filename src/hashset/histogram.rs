@@ -0,0 +1,145 @@
+/// Distribution of probe lengths
+///
+/// Bucket `i` counts how many lookups ended after exactly `i` collisions.
+/// The histogram grows to fit the longest probe sequence recorded.
+/// The benchmark records lookups from `HashTable::probe_stats`, so
+/// recording them changes neither the table nor its collision counter.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProbeHistogram {
+    counts: Vec<usize>,
+}
+
+impl ProbeHistogram {
+    /// creates an empty histogram
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// records one lookup that needed `collisions` collisions
+    pub fn record(&mut self, collisions: usize) {
+        if collisions >= self.counts.len() {
+            self.counts.resize(collisions + 1, 0);
+        }
+        self.counts[collisions] += 1;
+    }
+
+    /// adds all lookups recorded in `other` to this histogram
+    pub fn merge(&mut self, other: &ProbeHistogram) {
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+
+    /// removes all recorded lookups
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+
+    /// number of lookups with exactly `i` collisions at index `i`
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// total number of recorded lookups
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// total number of collisions over all recorded lookups
+    pub fn collisions(&self) -> usize {
+        self.counts.iter().enumerate().map(|(i, c)| i * c).sum()
+    }
+
+    /// average number of collisions per lookup
+    ///
+    /// returns NaN if no lookup was recorded
+    pub fn mean(&self) -> f64 {
+        self.collisions() as f64 / self.total() as f64
+    }
+
+    /// longest recorded probe sequence
+    ///
+    /// returns None if no lookup was recorded
+    pub fn max(&self) -> Option<usize> {
+        self.counts.iter().rposition(|&c| c > 0)
+    }
+
+    /// smallest number of collisions that at least `p` of all lookups did not exceed
+    ///
+    /// `p` is a fraction in [0, 1]. Returns None if no lookup was recorded
+    pub fn percentile(&self, p: f64) -> Option<usize> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let needed = f64::ceil(p * total as f64).max(1_f64) as usize;
+        let mut seen = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= needed {
+                return Some(i);
+            }
+        }
+        self.max()
+    }
+
+    /// cumulative distribution of probe lengths
+    ///
+    /// The value at index `i` is the fraction of lookups with at most `i` collisions
+    pub fn cdf(&self) -> Vec<f64> {
+        let total = self.total() as f64;
+        let mut seen = 0;
+        self.counts
+            .iter()
+            .map(|count| {
+                seen += count;
+                seen as f64 / total
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics() {
+        let mut histogram = ProbeHistogram::new();
+        assert_eq!(histogram.max(), None);
+        assert_eq!(histogram.percentile(0.5), None);
+        assert!(histogram.mean().is_nan());
+        for collisions in &[0, 0, 1, 3, 0, 1, 0, 0, 2, 0] {
+            histogram.record(*collisions);
+        }
+        assert_eq!(histogram.counts(), &[6, 2, 1, 1]);
+        assert_eq!(histogram.total(), 10);
+        assert_eq!(histogram.collisions(), 7);
+        assert_eq!(histogram.mean(), 0.7);
+        assert_eq!(histogram.max(), Some(3));
+        assert_eq!(histogram.percentile(0.0), Some(0));
+        assert_eq!(histogram.percentile(0.6), Some(0));
+        assert_eq!(histogram.percentile(0.61), Some(1));
+        assert_eq!(histogram.percentile(0.9), Some(2));
+        assert_eq!(histogram.percentile(1.0), Some(3));
+        assert_eq!(histogram.cdf(), vec![0.6, 0.8, 0.9, 1.0]);
+    }
+
+    #[test]
+    fn merge_and_clear() {
+        let mut a = ProbeHistogram::new();
+        let mut b = ProbeHistogram::new();
+        a.record(1);
+        b.record(0);
+        b.record(4);
+        a.merge(&b);
+        assert_eq!(a.counts(), &[1, 1, 0, 0, 1]);
+        b.merge(&ProbeHistogram::new());
+        assert_eq!(b.counts(), &[1, 0, 0, 0, 1]);
+        a.clear();
+        assert_eq!(a, ProbeHistogram::new());
+    }
+}
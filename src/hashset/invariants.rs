@@ -0,0 +1,140 @@
+use std::error::Error;
+use std::fmt;
+
+/// Broken internal invariant of a table, found by `check_invariants`
+///
+/// Buckets and nodes are identified by their index, as elements can
+/// not be printed in general. Any violation is a bug of the table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// an element of the bucket is stored more than once
+    Duplicate { bucket: usize },
+    /// an element is stored in the bucket, but a lookup of it does not reach it
+    Unreachable { bucket: usize },
+    /// a link to a bucket or node is out of bounds
+    OutOfBounds { index: usize },
+    /// a node or bucket is reached twice, by a cycle or by two chains
+    Cycle { index: usize },
+    /// the table counted a different number of elements, nodes or buckets than it stores
+    Count { expected: usize, found: usize },
+    /// the chain or tree of the bucket is not ordered or balanced as required
+    Order { bucket: usize },
+    /// the bucket is not laid out as required, e.g. a chain next to empty slots
+    Layout { bucket: usize },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvariantViolation::Duplicate { bucket } => {
+                write!(f, "an element of bucket {} is stored twice", bucket)
+            }
+            InvariantViolation::Unreachable { bucket } => {
+                write!(f, "the element of bucket {} can not be found", bucket)
+            }
+            InvariantViolation::OutOfBounds { index } => {
+                write!(f, "link to {} is out of bounds", index)
+            }
+            InvariantViolation::Cycle { index } => write!(f, "{} is reached twice", index),
+            InvariantViolation::Count { expected, found } => {
+                write!(f, "expected {} elements but found {}", expected, found)
+            }
+            InvariantViolation::Order { bucket } => {
+                write!(f, "bucket {} is not ordered or balanced", bucket)
+            }
+            InvariantViolation::Layout { bucket } => {
+                write!(f, "bucket {} is not laid out correctly", bucket)
+            }
+        }
+    }
+}
+
+impl Error for InvariantViolation {}
+
+/// checks the elements stored for one bucket
+///
+/// Every element has to belong to the bucket and be stored only once.
+/// Elements can not be hashed or ordered in general, so duplicates are
+/// found by comparing every pair
+pub fn check_bucket<T: PartialEq>(
+    bucket: usize,
+    values: &[T],
+    belongs: impl Fn(&T) -> bool,
+) -> Result<(), InvariantViolation> {
+    for (i, x) in values.iter().enumerate() {
+        if !belongs(x) {
+            return Err(InvariantViolation::Unreachable { bucket });
+        }
+        if values[..i].contains(x) {
+            return Err(InvariantViolation::Duplicate { bucket });
+        }
+    }
+    Ok(())
+}
+
+/// checks that a lookup of the element stored in `bucket` reaches it
+///
+/// `path` yields every bucket the lookup visits in order, with its element.
+/// The lookup has to reach `bucket` before any empty bucket and before
+/// any other bucket storing the same element
+pub fn check_probe_path<T: PartialEq>(
+    bucket: usize,
+    val: &T,
+    path: impl Iterator<Item = (usize, Option<T>)>,
+) -> Result<(), InvariantViolation> {
+    for (index, inside) in path {
+        if index == bucket {
+            return Ok(());
+        }
+        match inside {
+            None => return Err(InvariantViolation::Unreachable { bucket }),
+            Some(x) if x == *val => return Err(InvariantViolation::Duplicate { bucket }),
+            Some(_) => {}
+        }
+    }
+    Err(InvariantViolation::Unreachable { bucket })
+}
+
+/// checks that the table counted as many elements as it stores
+pub fn check_count(expected: usize, found: usize) -> Result<(), InvariantViolation> {
+    if expected != found {
+        return Err(InvariantViolation::Count { expected, found });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets() {
+        assert_eq!(check_bucket(0, &[1, 2, 3], |_| true), Ok(()));
+        assert_eq!(
+            check_bucket(4, &[1, 2, 1], |_| true),
+            Err(InvariantViolation::Duplicate { bucket: 4 })
+        );
+        assert_eq!(
+            check_bucket(4, &[1, 2], |x| *x == 1),
+            Err(InvariantViolation::Unreachable { bucket: 4 })
+        );
+    }
+
+    #[test]
+    fn probe_paths() {
+        let path = || vec![(3, Some(1)), (4, Some(2)), (5, None), (6, Some(2))].into_iter();
+        assert_eq!(check_probe_path(4, &2, path()), Ok(()));
+        assert_eq!(
+            check_probe_path(6, &2, path()),
+            Err(InvariantViolation::Duplicate { bucket: 6 })
+        );
+        assert_eq!(
+            check_probe_path(6, &7, path()),
+            Err(InvariantViolation::Unreachable { bucket: 6 })
+        );
+        assert_eq!(
+            check_probe_path(9, &7, vec![(3, Some(1))].into_iter()),
+            Err(InvariantViolation::Unreachable { bucket: 9 })
+        );
+    }
+}
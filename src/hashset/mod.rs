@@ -1,63 +1,145 @@
 //! Module containing everything relevant to hashsets
 //!
 //! This contains hashing functions, probing functions,
-//! HashTable implementations and HashTable builders
+//! HashTable implementations, HashTable builders,
+//! thread-safe HashTables, probe length histograms and invariant checks
 
+mod arena;
+mod avltree;
 mod chainingtable;
 mod coalescedtable;
+mod concurrent;
+mod error;
+mod growing;
 mod hashing;
+mod histogram;
+mod invariants;
 mod openaddressing;
 mod probing;
+pub mod sizing;
+#[cfg(test)]
+mod tests;
 
+pub use arena::*;
+pub use avltree::*;
 pub use chainingtable::*;
 pub use coalescedtable::*;
+pub use concurrent::*;
+pub use error::*;
+pub use growing::*;
 pub use hashing::*;
+pub use histogram::*;
+pub use invariants::*;
 pub use openaddressing::*;
 pub use probing::*;
+pub use sizing::InfeasibleSize;
 use std::marker::PhantomData;
 
 /// Number of buckets for the OpenAddressingTable and load_factor is based on
 pub const ELEMENT_COUNT: usize = 1 << 15;
 
+/// Successful outcome of inserting an element into a HashTable
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertOutcome {
+    /// the element was not in the HashTable and was inserted
+    Inserted,
+    /// the element was in the HashTable already and was not inserted again
+    AlreadyPresent,
+}
+
+/// Outcome of a single lookup, as returned by `HashTable::probe_stats`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProbeStats {
+    /// the element is in the HashTable
+    pub found: bool,
+    /// collisions counted during this lookup, like `HashTable::has` counts them
+    pub collisions: usize,
+}
+
 /// Generic HashTable as set datastructure
 ///
-/// The hashtable should only count collisions on calls for finding an element
-/// not on insertion. Every inserted element should only be saved once,
+/// The hashtable counts collisions on calls for finding an element separately
+/// from collisions on insertion, so inserting never changes the lookup collisions.
+/// Every inserted element should only be saved once,
 /// as only either having or not having the element is checked.
-/// The HashTable can not delete any entries or dynamically resize the table
+/// The HashTable can not delete any entries. Whether it grows while
+/// elements are inserted depends on the table, most keep their buckets
 pub trait HashTable<T> {
     /// checks if the element is in the set
     ///
     /// Should return false if the element can not be found and true if it can
     /// Needs to count every collision that occured during the check
     fn has(&mut self, val: &T) -> bool;
+    /// checks if the element is in the set without any bookkeeping
+    ///
+    /// Neither counts collisions nor changes the table, so it only needs a
+    /// shared reference. Self-organizing tables are not reordered.
+    /// Lookups are timed through this, so it should only search the element,
+    /// `probe_stats` is the lookup counting collisions
+    fn contains(&self, val: &T) -> bool;
+    /// checks if the element is in the set and returns the collisions of this lookup
+    ///
+    /// Collisions are counted like `has` counts them, but neither the
+    /// collision counter nor the table is changed
+    fn probe_stats(&self, val: &T) -> ProbeStats;
     /// resets collisions
     fn reset_collisions(&mut self);
     /// returns the number of collisions
     fn get_collisions(&self) -> usize;
+    /// resets collisions counted on insertion
+    fn reset_insert_collisions(&mut self);
+    /// returns the number of collisions counted on insertion
+    ///
+    /// Every bucket or node holding another element that had to be checked
+    /// before the element was inserted or found is counted as a collision
+    fn get_insert_collisions(&self) -> usize;
     /// inserts the element in the HashTable
     ///
-    /// returns true if the element is already in the HashTable
-    /// returns true if the element was successfully inserted
-    /// returns false iff the element cannot be inserted into the HashTable
+    /// returns AlreadyPresent if the element is already in the HashTable
+    /// returns Inserted if the element was successfully inserted
+    /// returns an error iff the element cannot be inserted into the HashTable
     ///
     /// ## Causes for failure
-    /// - the hashset is full
-    /// - a bad cycle in probing hindered insertion
-    fn insert(&mut self, val: &T) -> bool;
+    /// - the hashset is full: `HashTableError::Full`
+    /// - a bad cycle in probing hindered insertion: `HashTableError::ProbeCycle`
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError>;
     /// resize the number of buckets to most closely match the number of bytes used
     ///
     /// depending on the type of HashTable it could be hard to implement with
     /// 100% accuracy. Therefore the expected value of the number
-    /// of bytes can be used as an approximate value
-    fn resize_to_bytes(&mut self, bytes: usize, elements: usize);
+    /// of bytes can be used as an approximate value.
+    /// The sizes are derived from the types actually stored, see `sizing`.
+    /// Only the buckets and nodes are counted, not the fixed size of the table.
+    ///
+    /// Fails with `HashTableError::InfeasibleSize` if the table can not be
+    /// sized to `bytes` while holding `elements`
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError>;
+    /// returns the number of buckets
+    ///
+    /// Buckets are the slots an element can be hashed to
+    fn buckets(&self) -> usize;
+    /// returns the number of bytes used by the table
+    ///
+    /// Counts the table itself and everything it allocated on the heap,
+    /// as calculated from the sizes of the types actually stored.
+    /// Overhead of the allocator is not counted
+    fn memory_usage(&self) -> usize;
+    /// checks the internal invariants of the table
+    ///
+    /// Every element has to be stored once and be reachable by a lookup,
+    /// links have to stay in bounds and chains may not have cycles.
+    /// Counts kept by the table have to match the elements stored.
+    /// Takes time linear in the size of the table, so it is only meant
+    /// for tests and debugging
+    fn check_invariants(&self) -> Result<(), InvariantViolation>;
 }
 
 /// A generic builder for HashTables
 ///
 /// The trait can be used when a generic HashTable type is to be
-/// used as a function parameter
-pub trait HashTableBuilder<T> {
+/// used as a function parameter. Builders are shared between the
+/// threads of a benchmark, the tables they build are not
+pub trait HashTableBuilder<T>: Sync {
     /// returns an instance of HashTable
     fn build(&self) -> Box<dyn HashTable<T>>;
 }
@@ -69,9 +151,10 @@ pub trait HashTableBuilder<T> {
 /// Should be the most used one
 pub struct DefaultHashTableBuilder<T: PartialEq, H: HashTable<T> + Default> {
     // PhantomData is used to not actually save any data
-    // It is only used to save the type to be instantiated
-    table: PhantomData<H>,
-    t: PhantomData<T>,
+    // It is only used to save the type to be instantiated,
+    // a function pointer keeps the builder Sync for any table
+    table: PhantomData<fn() -> H>,
+    t: PhantomData<fn() -> T>,
 }
 
 impl<T: PartialEq, H: 'static + HashTable<T> + Default> HashTableBuilder<T>
@@ -1,5 +1,9 @@
-use super::{HashTable, Hasher, Prober, ELEMENT_COUNT};
+use super::{
+    check_probe_path, sizing, HashTable, HashTableError, Hasher, InfeasibleSize, InsertOutcome,
+    InvariantViolation, ProbeStats, Prober, ELEMENT_COUNT,
+};
 use std::marker::PhantomData;
+use std::mem::size_of;
 
 /// Simple and fast HashTable with OpenAddressing
 ///
@@ -8,6 +12,7 @@ use std::marker::PhantomData;
 /// used, insertion could fail even though not every bucket is used.
 pub struct OpenAddressingTable<T: PartialEq + Copy, P: Prober, H: Hasher<T>> {
     collisions: usize,
+    insert_collisions: usize,
     entries: [Option<T>; ELEMENT_COUNT],
     prober: PhantomData<P>,
     hasher: PhantomData<H>,
@@ -17,6 +22,7 @@ impl<T: PartialEq + Copy, P: Prober, H: Hasher<T>> Default for OpenAddressingTab
     fn default() -> Self {
         Self {
             collisions: 0,
+            insert_collisions: 0,
             entries: [None; ELEMENT_COUNT],
             prober: PhantomData,
             hasher: PhantomData,
@@ -28,24 +34,52 @@ impl<T: PartialEq + Copy, H: Hasher<T>, P: Prober> HashTable<T> for OpenAddressi
     /// probes table for value
     ///
     /// returns true iff value was inserted into HashTable
-    /// It will at maximum check a number of buckets equal to the 
+    /// It will at maximum check a number of buckets equal to the
     /// total number of buckets. It does not use cycle detection.
     /// While probing the hash+offset is wrapped around the end of the table.
     /// Every accessed non-empty bucket that did not contain the value
     /// searched for is counted as a collision
     fn has(&mut self, val: &T) -> bool {
+        let stats = self.probe_stats(val);
+        self.collisions += stats.collisions;
+        stats.found
+    }
+    /// probes table for value, see `has`
+    fn probe_stats(&self, val: &T) -> ProbeStats {
         let mut index = H::hash(val, self.entries.len());
         let mut attempts = 0;
         while attempts < self.entries.len() {
-            if let Some(inside) = self.entries[index] {
-                if inside == *val {
-                    return true;
+            match self.entries[index] {
+                Some(inside) if inside == *val => {
+                    return ProbeStats {
+                        found: true,
+                        collisions: attempts,
+                    }
                 }
-            } else {
-                return false;
+                Some(_) => {}
+                None => break,
+            }
+            attempts += 1;
+            index = (index + P::probe(attempts)) % self.entries.len();
+        }
+        ProbeStats {
+            found: false,
+            collisions: attempts,
+        }
+    }
+    /// probes table for value without counting collisions
+    ///
+    /// the number of attempts only bounds the probe sequence like in `has`
+    fn contains(&self, val: &T) -> bool {
+        let mut index = H::hash(val, self.entries.len());
+        let mut attempts = 0;
+        while attempts < self.entries.len() {
+            match self.entries[index] {
+                Some(inside) if inside == *val => return true,
+                Some(_) => {}
+                None => return false,
             }
             attempts += 1;
-            self.collisions += 1;
             index = (index + P::probe(attempts)) % self.entries.len();
         }
         false
@@ -58,41 +92,196 @@ impl<T: PartialEq + Copy, H: Hasher<T>, P: Prober> HashTable<T> for OpenAddressi
     fn get_collisions(&self) -> usize {
         self.collisions
     }
+    /// resets number of collisions on insertion
+    fn reset_insert_collisions(&mut self) {
+        self.insert_collisions = 0;
+    }
+    /// returns number of collisions on insertion
+    fn get_insert_collisions(&self) -> usize {
+        self.insert_collisions
+    }
     /// inserts the element in the HashTable if possible
-    /// 
-    /// returns true iff the value was inserted successfully
-    /// It will at maximum check a number of buckets equal to the 
+    ///
+    /// returns Inserted iff the value was inserted successfully
+    /// It will at maximum check a number of buckets equal to the
     /// total number of buckets. It does not use cycle detection.
-    /// Insertion is not optimized for performance.
-    /// Insertion does not count collisions.
-    fn insert(&mut self, val: &T) -> bool {
-        if self.has(val) {
-            return true;
-        }
+    /// The element is put into the first empty bucket, unless it is
+    /// found before reaching one. Every accessed non-empty bucket that
+    /// did not contain the value is counted as an insertion collision.
+    /// If no empty bucket was reached, fails with `HashTableError::Full`
+    /// if every bucket is occupied and `HashTableError::ProbeCycle` otherwise.
+    fn insert(&mut self, val: &T) -> Result<InsertOutcome, HashTableError> {
         let mut index = H::hash(val, self.entries.len());
         let mut attempts = 0;
         while attempts < self.entries.len() {
-            if self.entries[index].is_none() {
-                self.entries[index] = Some(*val);
-                return true;
+            match self.entries[index] {
+                Some(inside) if inside == *val => return Ok(InsertOutcome::AlreadyPresent),
+                Some(_) => {}
+                None => {
+                    self.entries[index] = Some(*val);
+                    return Ok(InsertOutcome::Inserted);
+                }
             }
             attempts += 1;
+            self.insert_collisions += 1;
             index = (index + P::probe(attempts)) % self.entries.len();
         }
-        false
+        if self.entries.iter().any(Option::is_none) {
+            Err(HashTableError::ProbeCycle)
+        } else {
+            Err(HashTableError::Full)
+        }
     }
-    /// fails if bytes unequal to 8*ELEMENT_COUNT (for T = u32)
+    /// fails if bytes unequal to ELEMENT_COUNT*size_of(Option<T>)
     ///
     /// As the OpenAddressingTable is backed by an array with static size,
     /// it can't be dynamically allocated or resized. Thusly this
-    /// method will fail if not called with bytes = 
-    /// ELEMENT_COUNT*size_of(Option<T>) and elements <= ELEMENT_COUNT
-    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) {
-        if elements > ELEMENT_COUNT {
-            panic!("trying to insert more elements than possible by constraint");
+    /// method will fail if not called with bytes for exactly
+    /// ELEMENT_COUNT buckets and elements <= ELEMENT_COUNT.
+    /// (for T = u32) a bucket has 8 bytes.
+    fn resize_to_bytes(&mut self, bytes: usize, elements: usize) -> Result<(), HashTableError> {
+        let buckets = sizing::inline_buckets(bytes, elements, size_of::<Option<T>>())?;
+        if buckets != ELEMENT_COUNT {
+            return Err(InfeasibleSize { bytes, elements }.into());
+        }
+        Ok(())
+    }
+    /// returns the number of buckets
+    fn buckets(&self) -> usize {
+        self.entries.len()
+    }
+    /// returns the size of the table
+    ///
+    /// the buckets are stored inline, so this is only the size of the struct
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+    }
+    /// checks that probing for every element reaches its bucket
+    ///
+    /// The probe sequence from the hash of an element may not pass an
+    /// empty bucket or another copy of the element before reaching it
+    fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        for (bucket, entry) in self.entries.iter().enumerate() {
+            if let Some(val) = entry {
+                check_probe_path(bucket, val, probe_path::<T, P, H>(&self.entries, val))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// buckets visited by a lookup of `val` in `entries`, with their elements
+///
+/// Probes like an OpenAddressingTable, at most once per bucket
+pub fn probe_path<'a, T: PartialEq + Copy, P: Prober, H: Hasher<T>>(
+    entries: &'a [Option<T>],
+    val: &T,
+) -> impl Iterator<Item = (usize, Option<T>)> + 'a {
+    let mut index = H::hash(val, entries.len().max(1));
+    (0..entries.len()).map(move |attempts| {
+        if attempts > 0 {
+            index = (index + P::probe(attempts)) % entries.len();
+        }
+        (index, entries[index])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::StuckProber;
+    use super::super::{LinearProber, ModHash, QuadraticProber, XorShiftHash};
+    use super::*;
+
+    #[test]
+    fn insert_and_find() {
+        let mut table = Box::<OpenAddressingTable<u32, QuadraticProber, XorShiftHash>>::default();
+        for x in 0..1000 {
+            assert_eq!(table.insert(&(x * 7)), Ok(InsertOutcome::Inserted));
+        }
+        for x in 0..1000 {
+            assert!(table.has(&(x * 7)));
+            assert!(!table.contains(&(x * 7 + 1)));
+            assert_eq!(table.insert(&(x * 7)), Ok(InsertOutcome::AlreadyPresent));
+        }
+    }
+
+    #[test]
+    fn counts_collisions_of_a_cluster() {
+        let mut table = Box::<OpenAddressingTable<u32, LinearProber, ModHash>>::default();
+        let size = ELEMENT_COUNT as u32;
+        // all values are hashed to bucket 0
+        for x in 0..4 {
+            table.insert(&(x * size)).unwrap();
         }
-        if bytes * std::mem::size_of::<Option<T>>() != ELEMENT_COUNT {
-            panic!("trying to resize to invalid size");
+        // the ith value was placed after i collisions
+        assert_eq!(table.get_insert_collisions(), 1 + 2 + 3);
+        assert!(table.has(&(3 * size)));
+        assert_eq!(table.get_collisions(), 3);
+        assert_eq!(
+            table.probe_stats(&(4 * size)),
+            ProbeStats {
+                found: false,
+                collisions: 4
+            }
+        );
+        table.reset_collisions();
+        table.reset_insert_collisions();
+        assert_eq!(table.get_collisions(), 0);
+        assert_eq!(table.get_insert_collisions(), 0);
+    }
+
+    #[test]
+    fn fails_when_full() {
+        let mut table = Box::<OpenAddressingTable<u32, LinearProber, ModHash>>::default();
+        for x in 0..ELEMENT_COUNT as u32 {
+            table.insert(&x).unwrap();
         }
+        assert_eq!(
+            table.insert(&(ELEMENT_COUNT as u32)),
+            Err(HashTableError::Full)
+        );
+        assert_eq!(table.insert(&0), Ok(InsertOutcome::AlreadyPresent));
+        assert!(!table.contains(&(ELEMENT_COUNT as u32)));
+    }
+
+    #[test]
+    fn detects_probe_cycles() {
+        let mut table = Box::<OpenAddressingTable<u32, StuckProber, ModHash>>::default();
+        table.insert(&0).unwrap();
+        assert_eq!(
+            table.insert(&(ELEMENT_COUNT as u32)),
+            Err(HashTableError::ProbeCycle)
+        );
+        assert!(table.contains(&0));
+    }
+
+    #[test]
+    fn only_resizes_to_its_own_size() {
+        let mut table = Box::<OpenAddressingTable<u32, LinearProber, ModHash>>::default();
+        let bytes = ELEMENT_COUNT * size_of::<Option<u32>>();
+        assert_eq!(table.resize_to_bytes(bytes, 10), Ok(()));
+        assert!(table.resize_to_bytes(bytes / 2, 10).is_err());
+        assert!(table.resize_to_bytes(bytes, ELEMENT_COUNT + 1).is_err());
+        assert_eq!(table.buckets(), ELEMENT_COUNT);
+    }
+
+    #[test]
+    fn detects_broken_probe_paths() {
+        let mut table = Box::<OpenAddressingTable<u32, LinearProber, ModHash>>::default();
+        table.insert(&0).unwrap();
+        table.insert(&(ELEMENT_COUNT as u32)).unwrap();
+        assert_eq!(table.check_invariants(), Ok(()));
+
+        table.entries[5] = Some(3);
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Unreachable { bucket: 5 })
+        );
+        table.entries[5] = None;
+        table.entries[2] = Some(0);
+        assert_eq!(
+            table.check_invariants(),
+            Err(InvariantViolation::Duplicate { bucket: 2 })
+        );
     }
 }
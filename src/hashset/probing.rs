@@ -41,3 +41,48 @@ impl Prober for QuadraticProber {
         i * i
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// number of different buckets visited from bucket 0 in a table of `size` buckets
+    fn visited<P: Prober>(size: usize) -> usize {
+        let mut seen = vec![false; size];
+        let mut index = 0;
+        seen[index] = true;
+        for attempt in 1..size {
+            index = (index + P::probe(attempt)) % size;
+            seen[index] = true;
+        }
+        seen.iter().filter(|x| **x).count()
+    }
+
+    #[test]
+    fn offsets() {
+        let offsets = |probe: fn(usize) -> usize| (0..5).map(probe).collect::<Vec<_>>();
+        assert_eq!(offsets(LinearProber::probe), vec![0, 1, 2, 3, 4]);
+        assert_eq!(offsets(QuadraticProber::probe), vec![0, 1, 4, 9, 16]);
+        assert_eq!(offsets(TriangularProber::probe), vec![0, 1, 3, 6, 10]);
+    }
+
+    #[test]
+    fn linear_visits_every_bucket() {
+        // offsets are added up, so linear probing visits the triangular numbers
+        for &size in &[1, 2, 16, 1024, 1 << 15] {
+            assert_eq!(visited::<LinearProber>(size), size);
+        }
+    }
+
+    #[test]
+    fn triangular_misses_buckets() {
+        assert!(visited::<TriangularProber>(16) < 16);
+        assert!(visited::<TriangularProber>(1 << 15) < 1 << 15);
+    }
+
+    #[test]
+    fn quadratic_misses_buckets() {
+        assert!(visited::<QuadraticProber>(16) < 16);
+        assert!(visited::<QuadraticProber>(1 << 15) < 1 << 15);
+    }
+}
@@ -0,0 +1,209 @@
+use std::error::Error;
+use std::fmt;
+
+/// Error returned if a table can not be sized to fit a number of bytes
+///
+/// Either the bytes do not suffice for the elements to be inserted
+/// or the table can not be sized to use exactly the bytes given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InfeasibleSize {
+    /// number of bytes the table should have used
+    pub bytes: usize,
+    /// number of elements the table should have been able to hold
+    pub elements: usize,
+}
+
+impl fmt::Display for InfeasibleSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cannot size table for {} elements to {} bytes",
+            self.elements, self.bytes
+        )
+    }
+}
+
+impl Error for InfeasibleSize {}
+
+/// Expected number of empty buckets
+///
+/// After inserting `elements` uniformly hashed elements into `buckets`
+/// buckets, a bucket is empty with probability ((m-1)/m)^n.
+/// Therefore m((m-1)/m)^n buckets are expected to be empty
+pub fn expected_empty_buckets(buckets: usize, elements: usize) -> f64 {
+    let m = buckets as f64;
+    m * ((m - 1_f64) / m).powf(elements as f64)
+}
+
+/// Expected number of elements not fitting into their bucket
+///
+/// Every bucket holds up to `inline` elements, all others overflow.
+/// The number of elements hashed to one bucket is binomially distributed
+/// with p = 1/m, so n - m*E[min(X, inline)] elements are expected to overflow.
+/// For `inline` = 1 this is n - (m - m((m-1)/m)^n)
+pub fn expected_overflowing(buckets: usize, elements: usize, inline: usize) -> f64 {
+    let n = elements as f64;
+    if buckets <= 1 {
+        return (n - inline as f64).max(0_f64);
+    }
+    let p = 1_f64 / buckets as f64;
+    // probability of exactly k elements in a bucket, starting at k = 0
+    let mut exactly = (1_f64 - p).powf(n);
+    // E[min(X, inline)] = sum of P(X > k) for k < inline
+    let mut more_than = 1_f64;
+    let mut expected_inline = 0_f64;
+    for k in 0..inline {
+        more_than -= exactly;
+        expected_inline += more_than.max(0_f64);
+        exactly *= (n - k as f64) / (k as f64 + 1_f64) * p / (1_f64 - p);
+    }
+    (n - buckets as f64 * expected_inline).max(0_f64)
+}
+
+/// Largest number of buckets whose expected size fits into `bytes`
+///
+/// `size` returns the expected number of bytes used with `m` buckets
+/// and has to grow monotonically in `m`. Fails if not even one bucket fits.
+/// Finds the value with binary search
+pub fn largest_fitting_buckets<F: Fn(usize) -> f64>(
+    bytes: usize,
+    elements: usize,
+    bucket_size: usize,
+    size: F,
+) -> Result<usize, InfeasibleSize> {
+    // We cannot allocate more buckets than if no element needed more space
+    let mut high = bytes / bucket_size.max(1);
+    let mut low = 0;
+    // invariant: size(low) fits (or low == 0), size(high + 1) does not fit
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if size(mid) <= bytes as f64 {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    if low == 0 {
+        return Err(InfeasibleSize { bytes, elements });
+    }
+    Ok(low)
+}
+
+/// Number of buckets for tables storing every element inline in a bucket
+///
+/// The size of such a table is buckets*bucket_size. Fails if
+/// fewer buckets than elements fit into `bytes`
+pub fn inline_buckets(
+    bytes: usize,
+    elements: usize,
+    bucket_size: usize,
+) -> Result<usize, InfeasibleSize> {
+    let buckets = bytes / bucket_size.max(1);
+    if buckets < elements.max(1) {
+        return Err(InfeasibleSize { bytes, elements });
+    }
+    Ok(buckets)
+}
+
+/// Number of buckets for tables storing every element in a separate node
+///
+/// The size of such a table is buckets*bucket_size + elements*node_size.
+/// Fails if not even one bucket fits into the remaining bytes
+pub fn chained_buckets(
+    bytes: usize,
+    elements: usize,
+    bucket_size: usize,
+    node_size: usize,
+) -> Result<usize, InfeasibleSize> {
+    let infeasible = InfeasibleSize { bytes, elements };
+    let available = bytes.checked_sub(elements * node_size).ok_or(infeasible)?;
+    let buckets = available / bucket_size.max(1);
+    if buckets == 0 {
+        return Err(infeasible);
+    }
+    Ok(buckets)
+}
+
+/// Number of buckets for tables storing one element inline and all others in nodes
+///
+/// See `bucketized_buckets` with one inline element
+pub fn separate_buckets(
+    bytes: usize,
+    elements: usize,
+    bucket_size: usize,
+    node_size: usize,
+) -> Result<usize, InfeasibleSize> {
+    bucketized_buckets(bytes, elements, 1, bucket_size, node_size)
+}
+
+/// Number of buckets for tables storing `inline` elements inline and all others in nodes
+///
+/// The size of such a table is buckets*bucket_size + overflowing*node_size,
+/// where only elements hashed to an already full bucket overflow.
+/// As every additional bucket saves at most `inline` nodes, this size grows
+/// monotonically in the number of buckets if `bucket_size` >= `inline`*`node_size`,
+/// so the number of buckets is found with binary search.
+pub fn bucketized_buckets(
+    bytes: usize,
+    elements: usize,
+    inline: usize,
+    bucket_size: usize,
+    node_size: usize,
+) -> Result<usize, InfeasibleSize> {
+    largest_fitting_buckets(bytes, elements, bucket_size, |m| {
+        m as f64 * bucket_size as f64 + expected_overflowing(m, elements, inline) * node_size as f64
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_sizing() {
+        assert_eq!(inline_buckets(800, 50, 8), Ok(100));
+        assert_eq!(inline_buckets(800, 100, 8), Ok(100));
+        assert_eq!(
+            inline_buckets(800, 101, 8),
+            Err(InfeasibleSize {
+                bytes: 800,
+                elements: 101
+            })
+        );
+        assert!(inline_buckets(4, 0, 8).is_err());
+    }
+
+    #[test]
+    fn chained_sizing() {
+        // 100 nodes of 8 bytes leave 200 bytes for buckets of 4 bytes
+        assert_eq!(chained_buckets(1000, 100, 4, 8), Ok(50));
+        assert!(chained_buckets(800, 100, 4, 8).is_err());
+        assert!(chained_buckets(700, 100, 4, 8).is_err());
+    }
+
+    #[test]
+    fn expectations() {
+        assert_eq!(expected_empty_buckets(10, 0), 10_f64);
+        assert!((expected_empty_buckets(1000, 1000) - 1000_f64 / std::f64::consts::E).abs() < 1.0);
+        // with one inline slot, overflow is n - (m - empty)
+        let (m, n) = (1000, 700);
+        let overflow = n as f64 - (m as f64 - expected_empty_buckets(m, n));
+        assert!((expected_overflowing(m, n, 1) - overflow).abs() < 1e-6);
+        assert_eq!(expected_overflowing(1, 5, 2), 3_f64);
+        assert!(expected_overflowing(1000, 700, 4) < expected_overflowing(1000, 700, 2));
+    }
+
+    #[test]
+    fn bucketized_sizing_fits() {
+        let (bytes, elements, inline) = (1 << 16, 4000, 4);
+        let (bucket_size, node_size) = (20, 8);
+        let buckets = bucketized_buckets(bytes, elements, inline, bucket_size, node_size).unwrap();
+        let size = |m: usize| {
+            m as f64 * bucket_size as f64
+                + expected_overflowing(m, elements, inline) * node_size as f64
+        };
+        assert!(size(buckets) <= bytes as f64);
+        assert!(size(buckets + 1) > bytes as f64);
+        assert!(bucketized_buckets(8, elements, inline, bucket_size, node_size).is_err());
+    }
+}
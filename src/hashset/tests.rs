@@ -0,0 +1,234 @@
+use super::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+
+/// Number of random operation sequences every table is checked with
+const SEQUENCES: u64 = 6;
+/// Operations of every sequence
+const OPERATIONS: usize = 3000;
+/// Operations between two checks of the invariants, after the first operations
+const CHECK_INTERVAL: usize = 500;
+
+/// Runs a generic check for every HashTable of this module
+///
+/// Every table is listed with a good and a bad hasher where both are
+/// interesting, probers and chain variants are covered by one hasher
+macro_rules! for_every_table {
+    ($check:ident) => {
+        $check::<OpenAddressingTable<u32, LinearProber, XorShiftHash>>();
+        $check::<OpenAddressingTable<u32, LinearProber, ModHash>>();
+        $check::<OpenAddressingTable<u32, QuadraticProber, MulHash>>();
+        $check::<OpenAddressingTable<u32, TriangularProber, XorShiftHash>>();
+        $check::<DirectChainingTable<u32, XorShiftHash>>();
+        $check::<DirectChainingTable<u32, ModHash, HeadInsertion>>();
+        $check::<DirectChainingTable<u32, MulHash, TailInsertion, MoveToFront>>();
+        $check::<DirectChainingTable<u32, ModHash, HeadInsertion, Transpose>>();
+        $check::<SortedChainingTable<u32, XorShiftHash>>();
+        $check::<SortedChainingTable<u32, ModHash>>();
+        $check::<SeparateChainingTable<u32, XorShiftHash>>();
+        $check::<SeparateChainingTable<u32, ModHash, HeadInsertion>>();
+        $check::<TreeifiedChainingTable<u32, XorShiftHash>>();
+        $check::<TreeifiedChainingTable<u32, ModHash, 2>>();
+        $check::<BucketizedChainingTable<u32, XorShiftHash, 1>>();
+        $check::<BucketizedChainingTable<u32, ModHash, 4>>();
+        $check::<CoalescedTable<u32, XorShiftHash>>();
+        $check::<CoalescedTable<u32, ModHash, Eisch>>();
+        $check::<CoalescedTable<u32, MulHash, Lich>>();
+        $check::<CoalescedTable<u32, XorShiftHash, Eich>>();
+        $check::<LinearHashingTable<u32, XorShiftHash>>();
+        $check::<LinearHashingTable<u32, ModHash>>();
+        $check::<RehashingChainingTable<u32, XorShiftHash>>();
+        $check::<GrowingOpenAddressingTable<u32, LinearProber, XorShiftHash>>();
+        $check::<GrowingOpenAddressingTable<u32, QuadraticProber, MulHash>>();
+        $check::<GrowingOpenAddressingTable<u32, LinearProber, ModHash, StopTheWorld>>();
+    };
+}
+
+/// never moves away from the bucket the value is hashed to
+pub(super) struct StuckProber;
+impl Prober for StuckProber {
+    fn probe(_: usize) -> usize {
+        0
+    }
+}
+
+/// collisions of a lookup of `val`, not counted by the table
+pub(super) fn lookup<H: HashTable<u32>>(table: &H, val: u32) -> usize {
+    table.probe_stats(&val).collisions
+}
+
+/// checks a table against HashSet under a random sequence of insertions and lookups
+///
+/// Values are drawn from a range about as large as the number of operations,
+/// so hits, misses and duplicates are all common. They are shifted by a
+/// random amount, so weak hashers map them to few buckets. Insertions may
+/// only fail if `may_fail` is set, a failed insertion must not change the
+/// elements of the table. Every lookup has to agree with the model and has
+/// to count the collisions `probe_stats` reported before it. The invariants
+/// of the table are checked after each of the first `checked` operations
+/// and after every CHECK_INTERVAL operations afterwards, as checking
+/// takes linear time.
+/// Returns the number of failed insertions
+fn check_against_model<H: HashTable<u32> + ?Sized>(
+    table: &mut H,
+    seed: u64,
+    may_fail: bool,
+    checked: usize,
+) -> usize {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let shift = [0, 4, 12][rng.gen_range(0, 3)];
+    let mut model = HashSet::new();
+    let mut failures = 0;
+    for operation in 0..OPERATIONS {
+        let val = rng.gen_range(0, OPERATIONS as u32) << shift;
+        if rng.gen_bool(0.5) {
+            match table.insert(&val) {
+                Ok(InsertOutcome::Inserted) => {
+                    assert!(model.insert(val), "{} was inserted twice", val)
+                }
+                Ok(InsertOutcome::AlreadyPresent) => {
+                    assert!(model.contains(&val), "{} was never inserted", val)
+                }
+                Err(e) => {
+                    assert!(may_fail, "inserting {} failed: {}", val, e);
+                    assert!(!model.contains(&val), "{} is present but failed", val);
+                    assert!(matches!(
+                        e,
+                        HashTableError::Full | HashTableError::ProbeCycle
+                    ));
+                    failures += 1;
+                }
+            }
+        } else {
+            let stats = table.probe_stats(&val);
+            let before = table.get_collisions();
+            let found = table.has(&val);
+            assert_eq!(found, model.contains(&val), "lookup of {}", val);
+            assert_eq!(stats.found, found);
+            assert_eq!(table.get_collisions() - before, stats.collisions);
+            assert_eq!(table.contains(&val), found);
+        }
+        if operation < checked || operation % CHECK_INTERVAL == 0 {
+            assert_eq!(
+                table.check_invariants(),
+                Ok(()),
+                "after operation {}",
+                operation
+            );
+        }
+    }
+    assert_eq!(table.check_invariants(), Ok(()));
+    for val in &model {
+        assert!(table.contains(val), "lost {}", val);
+    }
+    failures
+}
+
+/// tables sized like in the benchmark agree with the model
+fn matches_model<H: HashTable<u32> + Default>() {
+    for seed in 0..SEQUENCES {
+        let mut table = Box::new(H::default());
+        table
+            .resize_to_bytes(ELEMENT_COUNT << 3, OPERATIONS)
+            .expect("every table fits into the budget of the benchmark");
+        let buckets = table.buckets();
+        // weak hashers and quadratic probing may still run into probe cycles
+        check_against_model(table.as_mut(), seed, true, 0);
+        assert!(table.buckets() >= buckets);
+    }
+}
+
+/// tables with room for a small fraction of the values fill up consistently
+fn small_tables_fill_up<H: HashTable<u32> + Default>() {
+    for seed in 0..SEQUENCES {
+        let mut table = Box::new(H::default());
+        // the open addressing table can not be resized, it stays as it is
+        let _ = table.resize_to_bytes(1 << 10, 32);
+        // small tables are cheap to check while they fill up
+        let checked = if table.buckets() < ELEMENT_COUNT {
+            500
+        } else {
+            0
+        };
+        check_against_model(table.as_mut(), seed, true, checked);
+    }
+}
+
+#[test]
+fn every_table_matches_hash_set() {
+    for_every_table!(matches_model);
+}
+
+#[test]
+fn every_table_fills_up_consistently() {
+    for_every_table!(small_tables_fill_up);
+}
+
+#[test]
+fn good_hashers_never_fail() {
+    fn check<H: HashTable<u32> + Default>() {
+        for seed in 0..SEQUENCES {
+            let mut table = Box::new(H::default());
+            assert_eq!(check_against_model(table.as_mut(), seed, false, 0), 0);
+        }
+    }
+    check::<OpenAddressingTable<u32, LinearProber, XorShiftHash>>();
+    check::<DirectChainingTable<u32, ModHash>>();
+    check::<TreeifiedChainingTable<u32, ModHash>>();
+    check::<LinearHashingTable<u32, ModHash>>();
+    check::<GrowingOpenAddressingTable<u32, LinearProber, XorShiftHash>>();
+}
+
+#[test]
+fn small_inline_tables_report_full() {
+    fn check<H: HashTable<u32> + Default>() {
+        let mut table = H::default();
+        table.resize_to_bytes(1 << 10, 32).unwrap();
+        let buckets = table.buckets();
+        let mut inserted = 0;
+        for x in 0..(buckets as u32 * 2) {
+            match table.insert(&x) {
+                Ok(_) => inserted += 1,
+                Err(e) => assert_eq!(e, HashTableError::Full),
+            }
+        }
+        assert!(inserted >= buckets);
+    }
+    check::<CoalescedTable<u32, XorShiftHash>>();
+    check::<CoalescedTable<u32, XorShiftHash, Eich>>();
+}
+
+#[test]
+fn builders_build_default_tables() {
+    let builder = DefaultHashTableBuilder::<u32, DirectChainingTable<u32, XorShiftHash>>::default();
+    let mut table = builder.build();
+    assert_eq!(table.buckets(), ELEMENT_COUNT);
+    assert_eq!(check_against_model(table.as_mut(), 0, false, 0), 0);
+}
+
+#[test]
+fn concurrent_tables_match_hash_set() {
+    fn check<H: ConcurrentHashTable<u32> + Default>() {
+        for seed in 0..SEQUENCES {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let table = H::default();
+            let mut model = HashSet::new();
+            let mut collisions = 0;
+            for _ in 0..OPERATIONS {
+                let val = rng.gen_range(0, OPERATIONS as u32);
+                if rng.gen_bool(0.5) {
+                    let outcome = table.insert(&val, &mut collisions);
+                    assert_eq!(outcome == Ok(InsertOutcome::Inserted), model.insert(val));
+                } else {
+                    assert_eq!(table.has(&val, &mut collisions), model.contains(&val));
+                }
+            }
+            assert_eq!(table.check_invariants(), Ok(()));
+        }
+    }
+    check::<StripedChainingTable<u32, XorShiftHash>>();
+    check::<StripedChainingTable<u32, ModHash, 1>>();
+    check::<AtomicOpenAddressingTable<LinearProber, XorShiftHash>>();
+    check::<AtomicOpenAddressingTable<QuadraticProber, MulHash>>();
+}
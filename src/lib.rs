@@ -0,0 +1,11 @@
+//! Hash tables and the tools to measure them
+//!
+//! The `hashset` module contains every HashTable with its hashers,
+//! probers and builders, `timing` the timer used by the benchmarks.
+//! Neither depends on another crate. The benchmark driving all tables
+//! and writing the graphs is the binary of this crate, only built with
+//! the feature `benchmark`, so depend on this crate with
+//! `default-features = false` to leave out gnuplot and rand
+
+pub mod hashset;
+pub mod timing;
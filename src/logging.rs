@@ -1,7 +1,11 @@
 //! Module for helper logging functions
 //!
-//! This module contains all functions for writing output
+//! This module contains all functions for writing output.
+//! Every function works on BenchmarkResults and outputs all
+//! metrics they contain, in the order they were set.
+use crate::results::{BenchmarkResult, Metric, Sweep, HEAP_BYTES, MEMORY_USAGE};
 use gnuplot::{AxesCommon, Caption, Figure, Graph};
+use hashset_testing::hashset::ProbeHistogram;
 use std::fs::OpenOptions;
 use std::io::Write;
 
@@ -15,171 +19,342 @@ pub fn print_header(name: &str, load_factors: &[f64]) {
         let lambda = format!("{:.0}%", lambda * 100_f64);
         out.push_str(&format!("{:^5}", lambda));
         if i != load_factors.len() - 1 {
-            out.push_str("|");
+            out.push('|');
         }
     }
     println!("{}", out);
 }
 
+/// Prints one row of a table
+///
+/// # Example
+/// + collisions    | val | val | val | val
+fn print_row(name: &str, values: impl Iterator<Item = f64>) {
+    let mut out = format!("{:20}", name);
+    for (i, value) in values.enumerate() {
+        if i != 0 {
+            out.push('|');
+        }
+        out.push_str(&format!("{:^5.2}", value));
+    }
+    println!("{}", out);
+}
+
+/// returns the metrics of the first result
+///
+/// All results of one run are expected to contain the same metrics
+fn metrics_of<'a>(results: impl IntoIterator<Item = &'a BenchmarkResult>) -> Vec<Metric> {
+    results
+        .into_iter()
+        .next()
+        .map(|r| r.metrics().iter().map(|(metric, _)| *metric).collect())
+        .unwrap_or_default()
+}
+
+/// returns the histogram of successful or failed lookups of `result`
+fn histogram_of(result: &BenchmarkResult, success: bool) -> &ProbeHistogram {
+    if success {
+        &result.hits
+    } else {
+        &result.misses
+    }
+}
+
 /// Prints a table inclusive header
 ///
+/// Prints one row per metric. Rows starting with + are successful
+/// lookups, rows starting with - are failed lookups and rows starting
+/// with * are insertions. All results should belong to the same table
+///
 /// # Example
 /// Name            | 50% | 90% | 95% | 100%
 /// + collisions    | val | val | val | val
 /// + time[ns]      | val | val | val | val
 /// - collisions    | val | val | val | val
 /// - time[ns]      | val | val | val | val
-pub fn print_subtable(name: &str, stats: &[(f32, f64, f32, f64)], load_factors: &[f64]) {
+pub fn print_subtable(results: &[BenchmarkResult]) {
+    let name = results.first().map_or("", |r| &r.table);
+    let load_factors: Vec<f64> = results.iter().map(|r| r.load_factor).collect();
     println!();
-    print_header(name, load_factors);
-    let mut out = format!("{:20}", "+ collisions");
-    for i in 0..stats.len() {
-        out.push_str(&format!("{:^5.2}", stats[i].0));
-        if i != stats.len() - 1 {
-            out.push_str("|");
-        }
+    print_header(name, &load_factors);
+    for metric in metrics_of(results) {
+        print_row(
+            metric.label,
+            results.iter().map(|r| r.get(metric).unwrap_or(f64::NAN)),
+        );
     }
-    println!("{}", out);
-    let mut out = format!("{:20}", "+ time[ns]");
-    for i in 0..stats.len() {
-        out.push_str(&format!("{:^5.2}", stats[i].1));
-        if i != stats.len() - 1 {
-            out.push_str("|");
+}
+
+/// Prints every result using a different amount of memory than it was resized to
+///
+/// The bytes counted by the allocator are used if available, otherwise the
+/// memory used as calculated by the table. A result is reported if it deviates
+/// more than `tolerance` (relative) from the bytes it was resized to.
+/// Results that were not resized are skipped
+pub fn print_memory_fairness(all_results: &[Vec<BenchmarkResult>], tolerance: f64) {
+    println!();
+    let mut fair = true;
+    for result in all_results.iter().flatten() {
+        let budget = match result.bytes {
+            Some(bytes) => bytes as f64,
+            None => continue,
+        };
+        let used = match result.get(HEAP_BYTES).or_else(|| result.get(MEMORY_USAGE)) {
+            Some(used) => used,
+            None => continue,
+        };
+        let deviation = (used - budget) / budget;
+        if deviation.abs() > tolerance || deviation.is_nan() {
+            fair = false;
+            println!(
+                "unfair: {:20} at {:.0}% uses {:.0}B instead of {:.0}B ({:+.1}%)",
+                result.table,
+                result.load_factor * 100_f64,
+                used,
+                budget,
+                deviation * 100_f64
+            );
         }
     }
-    println!("{}", out);
-    let mut out = format!("{:20}", "- collisions");
-    for i in 0..stats.len() {
-        out.push_str(&format!("{:^5.2}", stats[i].2));
-        if i != stats.len() - 1 {
-            out.push_str("|");
-        }
+    if fair {
+        println!(
+            "fair: every table uses the bytes it was resized to (±{:.0}%)",
+            tolerance * 100_f64
+        );
     }
-    println!("{}", out);
-    let mut out = format!("{:20}", "- time[ns]");
-    for i in 0..stats.len() {
-        out.push_str(&format!("{:^5.2}", stats[i].3));
-        if i != stats.len() - 1 {
-            out.push_str("|");
+}
+
+/// Prints the parameter value with the lowest `metric` of a sweep
+///
+/// A sweep holds the results of one table for every value of a parameter,
+/// every value measured at the same load factors. Prints one line per
+/// load factor. Values with NaN as `metric` are ignored
+///
+/// # Example
+/// LICH XOR        | 90%: + collisions lowest at β=0.86 (0.68)
+pub fn print_sweep_optimum(name: &str, parameter: &str, sweep: &Sweep, metric: Metric) {
+    let load_factors = sweep.first().map_or(0, |(_, results)| results.len());
+    for l in 0..load_factors {
+        let best = sweep
+            .iter()
+            .filter_map(|(value, results)| {
+                let result = results.get(l)?;
+                Some((*value, result.load_factor, result.get(metric)?))
+            })
+            .filter(|(_, _, x)| !x.is_nan())
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        if let Some((value, load_factor, x)) = best {
+            println!(
+                "{:20}| {:.0}%: {} lowest at {}={:.2} ({:.2})",
+                name,
+                load_factor * 100_f64,
+                metric.label,
+                parameter,
+                value,
+                x
+            );
         }
     }
-    println!("{}", out);
 }
 
-/// Writes data to csv file "hashset_data.csv"
+/// Writes data to the csv file `path`
 ///
-/// Writes one file for all load factors combined.
-/// Size of load_factors and all Vecs in all_stats must be the same
-pub fn write_csv(all_stats: &[(String, Vec<(f32, f64, f32, f64)>)], load_factors: &[f64]) {
+/// Writes one row per table and load factor, starting with the
+/// metadata of the measurement followed by every metric
+pub fn write_csv(path: &str, all_results: &[Vec<BenchmarkResult>]) {
     let mut file = OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(true)
-        .open("hashset_data.csv")
+        .open(path)
         .expect("Could not open file to write output analysis to");
-    let mut header = String::new();
-    header.push_str("\"Name\",");
-    for lambda in load_factors {
-        let percentage = format!("{:.0}%", lambda * 100_f64);
-        header.push_str(
-            &format!("\"Success Collisions({0})\",\"Success Time({0})[ns]\"\"Failures Collisions({0})\",\"Failures Time({0})[ns]\",", percentage));
+    let metrics = metrics_of(all_results.iter().flatten());
+    let mut header =
+        String::from("\"Name\",\"Load factor\",\"Elements\",\"Buckets\",\"Bytes\",\"Seed\"");
+    for metric in &metrics {
+        header.push_str(&format!(",\"{}[{}]\"", metric.name, metric.unit));
     }
     header.push_str("\r\n");
     file.write_all(header.as_bytes())
         .expect("Could not write to file");
-    for (name, stats) in all_stats {
-        let mut f = format!("\"{}\"", name);
-        for stat in stats {
-            f.push_str(&format!(",{},{},{},{}", stat.0, stat.1, stat.2, stat.3));
+    for result in all_results.iter().flatten() {
+        let mut f = format!(
+            "\"{}\",{},{},{},{},{}",
+            result.table,
+            result.load_factor,
+            result.elements,
+            result.buckets,
+            result.bytes.map_or(String::new(), |b| b.to_string()),
+            result.seed
+        );
+        for metric in &metrics {
+            f.push_str(&format!(",{}", result.get(*metric).unwrap_or(f64::NAN)));
         }
-        f.push_str("\n");
+        f.push('\n');
         file.write_all(f.as_bytes())
             .expect("Could not write to file");
     }
 }
 
-/// Writes graph pngs in the graphs subfolder
+/// Writes graph pngs in the folder `directory`
 ///
-/// Writes separate graphs for collision on success,
-/// collisions on failure, time on success + time on failure
-pub fn write_graphs(
-    all_stats: &[(String, Vec<(f32, f64, f32, f64)>)],
-    load_factors: &[f64],
-    element_count: usize,
-) {
-    let mut fg = Figure::new();
-    let ax = fg
-        .axes2d()
-        .set_title("Collisions on success", &[])
-        .set_legend(Graph(0.5), Graph(0.9), &[], &[])
-        .set_x_label("Number of elements", &[])
-        .set_y_label("Collisions", &[]);
-    for (name, stats) in all_stats {
-        ax.lines(
-            load_factors
-                .iter()
-                .map(|x| (x * element_count as f64) as usize),
-            stats.iter().map(|x| x.0),
-            &[Caption(&name)],
-        );
+/// Writes a separate graph for every metric, named after the metric.
+/// Every table is one line over the number of elements inserted
+pub fn write_graphs(directory: &str, all_results: &[Vec<BenchmarkResult>]) {
+    std::fs::create_dir_all(directory).expect("Could not create directory for graphs");
+    for metric in metrics_of(all_results.iter().flatten()) {
+        let mut fg = Figure::new();
+        let ax = fg
+            .axes2d()
+            .set_title(metric.name, &[])
+            .set_legend(Graph(0.5), Graph(0.9), &[], &[])
+            .set_x_label("Number of elements", &[])
+            .set_y_label(metric.unit, &[]);
+        for results in all_results {
+            let name = results.first().map_or("", |r| &r.table);
+            ax.lines(
+                results.iter().map(|r| r.elements),
+                results.iter().map(|r| r.get(metric).unwrap_or(f64::NAN)),
+                &[Caption(name)],
+            );
+        }
+        fg.save_to_png(format!("{}/{}.png", directory, metric.file), 1920, 1080)
+            .expect("Could not save file");
     }
-    fg.save_to_png("./graphs/successful_collisions.png", 1920, 1080)
-        .expect("Could not save file");
+}
 
+/// Writes a graph of `metric` over the swept parameter in the graphs subfolder
+///
+/// Every sweep is named and holds the results of one table for every value
+/// of the parameter. Every sweep and load factor is one line.
+/// The graph is saved as "./graphs/{file}_{metric file}.png"
+pub fn write_sweep_graph(file: &str, parameter: &str, sweeps: &[(String, Sweep)], metric: Metric) {
     let mut fg = Figure::new();
     let ax = fg
         .axes2d()
-        .set_title("Collisions on failure", &[])
-        .set_legend(Graph(0.5), Graph(0.9), &[], &[])
-        .set_x_label("Number of elements", &[])
-        .set_y_label("Collisions", &[]);
-    for (name, stats) in all_stats {
-        ax.lines(
-            load_factors
-                .iter()
-                .map(|x| (x * element_count as f64) as usize),
-            stats.iter().map(|x| x.2),
-            &[Caption(&name)],
-        );
+        .set_title(&format!("{} over {}", metric.name, parameter), &[])
+        .set_legend(Graph(0.9), Graph(0.9), &[], &[])
+        .set_x_label(parameter, &[])
+        .set_y_label(metric.unit, &[]);
+    for (name, sweep) in sweeps {
+        let load_factors = sweep.first().map_or(0, |(_, results)| results.len());
+        for l in 0..load_factors {
+            let load_factor = sweep[0].1[l].load_factor;
+            ax.lines(
+                sweep.iter().map(|(value, _)| *value),
+                sweep.iter().map(|(_, results)| {
+                    results
+                        .get(l)
+                        .and_then(|r| r.get(metric))
+                        .unwrap_or(f64::NAN)
+                }),
+                &[Caption(&format!("{} {:.0}%", name, load_factor * 100_f64))],
+            );
+        }
     }
-    fg.save_to_png("./graphs/failure_collisions.png", 1920, 1080)
+    fg.save_to_png(format!("./graphs/{}_{}.png", file, metric.file), 1920, 1080)
         .expect("Could not save file");
+}
 
-    let mut fg = Figure::new();
-    let ax = fg
-        .axes2d()
-        .set_title("Time on success", &[])
-        .set_legend(Graph(0.5), Graph(0.9), &[], &[])
-        .set_x_label("Number of elements", &[])
-        .set_y_label("time[ns]", &[]);
-    for (name, stats) in all_stats {
-        ax.lines(
-            load_factors
-                .iter()
-                .map(|x| (x * element_count as f64) as usize),
-            stats.iter().map(|x| x.1),
-            &[Caption(&name)],
-        );
+/// Writes the probe length distributions to csv file "hashset_distributions.csv"
+///
+/// Writes one row per table, load factor and kind of lookup.
+/// Every row holds the number of lookups with 0, 1, 2, ... collisions,
+/// rows are padded with zeros to the longest probe sequence recorded
+pub fn write_distributions_csv(all_results: &[Vec<BenchmarkResult>]) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open("hashset_distributions.csv")
+        .expect("Could not open file to write output analysis to");
+    let longest = all_results
+        .iter()
+        .flatten()
+        .map(|r| usize::max(r.hits.counts().len(), r.misses.counts().len()))
+        .max()
+        .unwrap_or(0);
+    let mut header = String::from("\"Name\",\"Load factor\",\"Lookup\"");
+    for i in 0..longest {
+        header.push_str(&format!(",\"{} collisions\"", i));
     }
-    fg.save_to_png("./graphs/successful_time.png", 1920, 1080)
-        .expect("Could not save file");
+    header.push_str("\r\n");
+    file.write_all(header.as_bytes())
+        .expect("Could not write to file");
+    for result in all_results.iter().flatten() {
+        for (kind, success) in &[("Success", true), ("Failure", false)] {
+            let histogram = histogram_of(result, *success);
+            let mut f = format!("\"{}\",{},\"{}\"", result.table, result.load_factor, kind);
+            for i in 0..longest {
+                f.push_str(&format!(",{}", histogram.counts().get(i).unwrap_or(&0)));
+            }
+            f.push('\n');
+            file.write_all(f.as_bytes())
+                .expect("Could not write to file");
+        }
+    }
+}
 
-    let mut fg = Figure::new();
-    let ax = fg
-        .axes2d()
-        .set_title("Time on failure", &[])
-        .set_legend(Graph(0.5), Graph(0.9), &[], &[])
-        .set_x_label("Number of elements", &[])
-        .set_y_label("time[ns]", &[]);
-    for (name, stats) in all_stats {
-        ax.lines(
-            load_factors
-                .iter()
-                .map(|x| (x * element_count as f64) as usize),
-            stats.iter().map(|x| x.3),
-            &[Caption(name)],
-        );
+/// Writes probe length histograms and CDFs in the graphs/distributions subfolder
+///
+/// Writes four graphs for every table: histogram and CDF of the probe
+/// lengths on success and on failure. Every graph has one line per load factor.
+/// Histograms use a logarithmic scale, as the tail is what matters most
+pub fn write_distribution_graphs(all_results: &[Vec<BenchmarkResult>]) {
+    std::fs::create_dir_all("./graphs/distributions")
+        .expect("Could not create directory for graphs");
+    for results in all_results {
+        let name = results.first().map_or("", |r| &r.table);
+        let file_name = name.replace(' ', "_").to_lowercase();
+        for (kind, success) in &[("success", true), ("failure", false)] {
+            let mut fg = Figure::new();
+            let ax = fg
+                .axes2d()
+                .set_title(&format!("Probe lengths on {} ({})", kind, name), &[])
+                .set_legend(Graph(0.9), Graph(0.9), &[], &[])
+                .set_x_label("Collisions", &[])
+                .set_y_label("Fraction of lookups", &[])
+                .set_y_log(Some(10_f64));
+            for result in results {
+                let histogram = histogram_of(result, *success);
+                let total = histogram.total() as f64;
+                ax.lines(
+                    0..histogram.counts().len(),
+                    histogram.counts().iter().map(|c| *c as f64 / total),
+                    &[Caption(&format!("{:.0}%", result.load_factor * 100_f64))],
+                );
+            }
+            fg.save_to_png(
+                format!(
+                    "./graphs/distributions/{}_{}_histogram.png",
+                    file_name, kind
+                ),
+                1920,
+                1080,
+            )
+            .expect("Could not save file");
+
+            let mut fg = Figure::new();
+            let ax = fg
+                .axes2d()
+                .set_title(&format!("CDF of probe lengths on {} ({})", kind, name), &[])
+                .set_legend(Graph(0.9), Graph(0.5), &[], &[])
+                .set_x_label("Collisions", &[])
+                .set_y_label("Fraction of lookups", &[]);
+            for result in results {
+                let cdf = histogram_of(result, *success).cdf();
+                ax.lines(
+                    0..cdf.len(),
+                    cdf,
+                    &[Caption(&format!("{:.0}%", result.load_factor * 100_f64))],
+                );
+            }
+            fg.save_to_png(
+                format!("./graphs/distributions/{}_{}_cdf.png", file_name, kind),
+                1920,
+                1080,
+            )
+            .expect("Could not save file");
+        }
     }
-    fg.save_to_png("./graphs/failure_time.png", 1920, 1080)
-        .expect("Could not save file");
 }
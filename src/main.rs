@@ -5,15 +5,29 @@
 //! load factors tested can be adjusted by changing the constant `LOAD_FACTORS`
 
 extern crate gnuplot;
+extern crate hashset_testing;
 extern crate rand;
 
-pub mod hashset;
+pub mod allocator;
 pub mod logging;
+pub mod results;
+pub mod zipf;
 
-use hashset::*;
+use allocator::allocated_bytes;
+use hashset_testing::hashset::*;
+use hashset_testing::timing::{Clock, Timer};
 use logging::*;
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng, SeedableRng};
+use results::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
+use zipf::Zipf;
+
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static GLOBAL: allocator::CountingAllocator = allocator::CountingAllocator;
 
 /// Helper function to get an instance of a DefaultHashTableBuilder for the given HashTable
 fn get_builder<T: PartialEq + 'static, H: 'static + HashTable<T> + Default>(
@@ -21,6 +35,31 @@ fn get_builder<T: PartialEq + 'static, H: 'static + HashTable<T> + Default>(
     Box::new(DefaultHashTableBuilder::<T, H>::default())
 }
 
+/// Helper function to get an instance of a DefaultConcurrentHashTableBuilder for the given table
+fn get_concurrent_builder<T: 'static, H: 'static + ConcurrentHashTable<T> + Default>(
+) -> Box<dyn ConcurrentHashTableBuilder<T>> {
+    Box::new(DefaultConcurrentHashTableBuilder::<T, H>::default())
+}
+
+/// Helper function to register a HashTable for the main run
+///
+/// Collisions are counted through the builder, times are measured by
+/// time_static monomorphized for `H`, so they do not include virtual calls
+fn register<H: 'static + HashTable<u32> + Default>(name: &str) -> Registered {
+    (get_builder::<u32, H>(), name.to_owned(), time_static::<H>)
+}
+
+/// One builder for every value of a swept parameter, with the value
+type SweepBuilders = Vec<(f64, Box<dyn HashTableBuilder<u32>>)>;
+/// Times the tables of a builder and records the times in a result
+type TimeFn = fn(&dyn HashTableBuilder<u32>, bool, &mut BenchmarkResult);
+/// Builder, name and timing function of a table of the main run
+type Registered = (Box<dyn HashTableBuilder<u32>>, String, TimeFn);
+/// Builder, name, timing function and seed of the first iteration of every measured table
+type SeededBuilders<'a> = [(&'a dyn HashTableBuilder<u32>, String, TimeFn, u64)];
+/// Measurement of one table at one load factor with one seed
+type StatsFn = fn(&dyn HashTableBuilder<u32>, &str, f64, u64) -> BenchmarkResult;
+
 /// Resizes every type of HashTable, so they take up
 /// nearly the same space in memory
 const RESIZE_TO_MAKE_FAIR: bool = true;
@@ -30,223 +69,1190 @@ const LOAD_FACTORS: [f64; 32] = [
     0.01, 0.02, 0.03, 0.04, 0.05, 0.06, 0.07, 0.08, 0.09, 0.1, 0.11, 0.12, 0.13, 0.14, 0.15, 0.16,
     0.17, 0.18, 0.19, 0.2, 0.21, 0.22, 0.23, 0.24, 0.25, 0.26, 0.27, 0.28, 0.29, 0.3, 0.31, 0.32,
 ];
+/// Maximum relative deviation of the memory used by a table from the
+/// bytes it was resized to, before it is reported as unfair
+const FAIRNESS_TOLERANCE: f64 = 0.05;
 /// How many tests to do at each load factor
 const ITERATIONS_PER_LOAD_FACTOR: usize = 50;
+/// Unmeasured rounds before every timing, to warm up caches and branch predictors
+const WARMUP_ROUNDS: usize = 2;
+/// Measured rounds of every timing, outliers are rejected
+const TIMING_SAMPLES: usize = 7;
+/// Times the tables of the main run through their concrete type.
+/// Otherwise they are timed through `Box<dyn HashTable>`, like collisions
+/// are counted, to compare the overhead of virtual calls
+const STATIC_DISPATCH: bool = true;
+/// Checks the invariants of every table after every insertion and lookup
+/// whose collisions are counted, panicking on the first violation. Very
+/// slow, only meant for debugging a table. Timed operations are not checked
+const CHECK_INVARIANTS: bool = false;
+/// Times with the cycle counter of the processor instead of the monotonic clock,
+/// only available on x86_64
+const USE_CYCLE_COUNTER: bool = false;
+/// Counts the collisions of all tables, load factors and iterations
+/// on every available core. Timings are always measured serially
+const PARALLEL_COLLISIONS: bool = true;
+/// Writes a histogram and a CDF graph of the probe lengths
+/// for every table, with one line per load factor
+const WRITE_DISTRIBUTION_GRAPHS: bool = true;
+/// Measures coalesced tables with a cellar for every address factor
+/// in ADDRESS_FACTORS to find the optimal size of the cellar
+const SWEEP_ADDRESS_FACTORS: bool = true;
+/// Address factors (address region / all buckets) tested by the sweep
+const ADDRESS_FACTORS: [f64; 26] = [
+    0.5, 0.52, 0.54, 0.56, 0.58, 0.6, 0.62, 0.64, 0.66, 0.68, 0.7, 0.72, 0.74, 0.76, 0.78, 0.8,
+    0.82, 0.84, 0.86, 0.88, 0.9, 0.92, 0.94, 0.96, 0.98, 1.0,
+];
+/// Load factors the address factors are compared at
+///
+/// If RESIZE_TO_MAKE_FAIR is set, 32% fills about 96% of all buckets
+/// of a coalesced table
+const ADDRESS_FACTOR_LOAD_FACTORS: [f64; 3] = [0.24, 0.28, 0.32];
+/// Measures chaining tables with lookups drawn from a Zipf distribution,
+/// to compare self-organizing chains with static chains
+const MEASURE_SKEWED_LOOKUPS: bool = true;
+/// Exponent of the Zipf distribution of the skewed lookups
+const ZIPF_EXPONENT: f64 = 1.0;
+/// Number of skewed lookups per measurement
+const SKEWED_LOOKUPS: usize = 1 << 16;
+/// Load factors of the skewed lookups
+///
+/// Tables are not resized for skewed lookups, so chains grow longer than
+/// one element on average and reordering them can make a difference
+const SKEWED_LOAD_FACTORS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+/// Measures tables with keys all hashed to the same bucket,
+/// to compare the worst case of different collision resolutions
+const MEASURE_ADVERSARIAL_KEYS: bool = true;
+/// Load factors of the adversarial keys
+///
+/// Tables are not resized for adversarial keys, as every key
+/// is hashed to the same bucket anyway
+const ADVERSARIAL_LOAD_FACTORS: [f64; 5] = [0.01, 0.02, 0.05, 0.1, 0.2];
+/// Measures chaining tables filled with more elements than buckets,
+/// to compare sorted with unsorted chains
+const MEASURE_LONG_CHAINS: bool = true;
+/// Load factors of the long chains
+///
+/// Tables are not resized, so a load factor of 1 means one element per bucket
+const LONG_CHAIN_LOAD_FACTORS: [f64; 6] = [0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+/// Measures the time of every single insertion into growing tables,
+/// to compare incremental growth with stop-the-world rehashing
+const MEASURE_GROWTH: bool = true;
+/// Load factors of the growing tables
+///
+/// Growing tables start with INITIAL_BUCKETS buckets and are not resized,
+/// a load factor of 1 means ELEMENT_COUNT elements are inserted
+const GROWTH_LOAD_FACTORS: [f64; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+/// Measures the throughput of thread-safe tables used by several
+/// threads at once, for every number of threads in THREAD_COUNTS
+const MEASURE_CONCURRENCY: bool = true;
+/// Numbers of threads sharing one table
+const THREAD_COUNTS: [usize; 4] = [1, 2, 4, 8];
+/// Load factors of the thread-safe tables
+///
+/// If RESIZE_TO_MAKE_FAIR is set, they are resized like every other table
+const CONCURRENT_LOAD_FACTORS: [f64; 3] = [0.1, 0.2, 0.3];
+/// Number of lookups every thread does per measurement,
+/// half of them of inserted elements
+const LOOKUPS_PER_THREAD: usize = 1 << 16;
 
 fn main() {
     // All variants of HashTable possible in this module
-    let tables: Vec<(Box<dyn HashTableBuilder<u32>>, String)> = vec![
-        (
-            get_builder::<u32, OpenAddressingTable<u32, QuadraticProber, MulHash>>(),
-            "Quadratic Mul".to_owned(),
-        ),
-        (
-            get_builder::<u32, OpenAddressingTable<u32, QuadraticProber, ModHash>>(),
-            "Quadratic Mod".to_owned(),
-        ),
-        (
-            get_builder::<u32, OpenAddressingTable<u32, QuadraticProber, XorShiftHash>>(),
-            "Quadratic XOR".to_owned(),
-        ),
-        (
-            get_builder::<u32, OpenAddressingTable<u32, LinearProber, MulHash>>(),
-            "Linear Mul".to_owned(),
-        ),
-        (
-            get_builder::<u32, OpenAddressingTable<u32, LinearProber, ModHash>>(),
-            "Linear Mod".to_owned(),
-        ),
-        (
-            get_builder::<u32, OpenAddressingTable<u32, LinearProber, XorShiftHash>>(),
-            "Linear XOR".to_owned(),
-        ),
-        (
-            get_builder::<u32, OpenAddressingTable<u32, TriangularProber, MulHash>>(),
-            "Triangular Mul".to_owned(),
-        ),
-        (
-            get_builder::<u32, OpenAddressingTable<u32, TriangularProber, ModHash>>(),
-            "Triangular Mod".to_owned(),
-        ),
-        (
-            get_builder::<u32, OpenAddressingTable<u32, TriangularProber, XorShiftHash>>(),
-            "Triangular XOR".to_owned(),
-        ),
-        (
-            get_builder::<u32, DirectChainingTable<u32, MulHash>>(),
-            "Direct Mul".to_owned(),
-        ),
-        (
-            get_builder::<u32, DirectChainingTable<u32, ModHash>>(),
-            "Direct Mod".to_owned(),
-        ),
-        (
-            get_builder::<u32, DirectChainingTable<u32, XorShiftHash>>(),
-            "Direct XOR".to_owned(),
-        ),
-        (
-            get_builder::<u32, SeparateChainingTable<u32, MulHash>>(),
-            "Separate Mul".to_owned(),
-        ),
-        (
-            get_builder::<u32, SeparateChainingTable<u32, ModHash>>(),
-            "Separate Mod".to_owned(),
-        ),
-        (
-            get_builder::<u32, SeparateChainingTable<u32, XorShiftHash>>(),
-            "Separate XOR".to_owned(),
-        ),
-        (
-            get_builder::<u32, CoalescedTable<u32, MulHash>>(),
-            "Coalesced Mul".to_owned(),
-        ),
-        (
-            get_builder::<u32, CoalescedTable<u32, ModHash>>(),
-            "Coalesced Mod".to_owned(),
-        ),
-        (
-            get_builder::<u32, CoalescedTable<u32, XorShiftHash>>(),
-            "Coalesced XOR".to_owned(),
-        ),
+    let tables: Vec<Registered> = vec![
+        register::<OpenAddressingTable<u32, QuadraticProber, MulHash>>("Quadratic Mul"),
+        register::<OpenAddressingTable<u32, QuadraticProber, ModHash>>("Quadratic Mod"),
+        register::<OpenAddressingTable<u32, QuadraticProber, XorShiftHash>>("Quadratic XOR"),
+        register::<OpenAddressingTable<u32, LinearProber, MulHash>>("Linear Mul"),
+        register::<OpenAddressingTable<u32, LinearProber, ModHash>>("Linear Mod"),
+        register::<OpenAddressingTable<u32, LinearProber, XorShiftHash>>("Linear XOR"),
+        register::<OpenAddressingTable<u32, TriangularProber, MulHash>>("Triangular Mul"),
+        register::<OpenAddressingTable<u32, TriangularProber, ModHash>>("Triangular Mod"),
+        register::<OpenAddressingTable<u32, TriangularProber, XorShiftHash>>("Triangular XOR"),
+        register::<DirectChainingTable<u32, MulHash>>("Direct Mul"),
+        register::<DirectChainingTable<u32, ModHash>>("Direct Mod"),
+        register::<DirectChainingTable<u32, XorShiftHash>>("Direct XOR"),
+        register::<DirectChainingTable<u32, XorShiftHash, HeadInsertion>>("Direct Head XOR"),
+        register::<SortedChainingTable<u32, MulHash>>("Sorted Mul"),
+        register::<SortedChainingTable<u32, ModHash>>("Sorted Mod"),
+        register::<SortedChainingTable<u32, XorShiftHash>>("Sorted XOR"),
+        register::<SeparateChainingTable<u32, MulHash>>("Separate Mul"),
+        register::<SeparateChainingTable<u32, ModHash>>("Separate Mod"),
+        register::<SeparateChainingTable<u32, XorShiftHash>>("Separate XOR"),
+        register::<SeparateChainingTable<u32, XorShiftHash, HeadInsertion>>("Separate Head XOR"),
+        register::<TreeifiedChainingTable<u32, XorShiftHash>>("Treeified XOR"),
+        register::<BucketizedChainingTable<u32, XorShiftHash, 2>>("Bucketized 2 XOR"),
+        register::<BucketizedChainingTable<u32, XorShiftHash, 4>>("Bucketized 4 XOR"),
+        register::<BucketizedChainingTable<u32, XorShiftHash, 8>>("Bucketized 8 XOR"),
+        register::<CoalescedTable<u32, MulHash>>("Coalesced Mul"),
+        register::<CoalescedTable<u32, ModHash>>("Coalesced Mod"),
+        register::<CoalescedTable<u32, XorShiftHash>>("Coalesced XOR"),
+        register::<CoalescedTable<u32, XorShiftHash, Eisch>>("Coalesced EISCH XOR"),
+        register::<CoalescedTable<u32, XorShiftHash, Lich>>("Coalesced LICH XOR"),
+        register::<CoalescedTable<u32, XorShiftHash, Eich>>("Coalesced EICH XOR"),
     ];
     generate_stats(tables);
+
+    if SWEEP_ADDRESS_FACTORS {
+        let sweeps: Vec<(SweepBuilders, String)> = vec![
+            (
+                get_address_factor_builders::<XorShiftHash, Lich>(),
+                "LICH XOR".to_owned(),
+            ),
+            (
+                get_address_factor_builders::<XorShiftHash, Eich>(),
+                "EICH XOR".to_owned(),
+            ),
+        ];
+        sweep_address_factors(sweeps);
+    }
+
+    if MEASURE_SKEWED_LOOKUPS {
+        let tables: Vec<(Box<dyn HashTableBuilder<u32>>, String)> = vec![
+            (
+                get_builder::<u32, DirectChainingTable<u32, XorShiftHash>>(),
+                "Direct XOR".to_owned(),
+            ),
+            (
+                get_builder::<u32, DirectChainingTable<u32, XorShiftHash, HeadInsertion>>(),
+                "Direct Head XOR".to_owned(),
+            ),
+            (
+                get_builder::<
+                    u32,
+                    DirectChainingTable<u32, XorShiftHash, TailInsertion, MoveToFront>,
+                >(),
+                "Direct MTF XOR".to_owned(),
+            ),
+            (
+                get_builder::<u32, DirectChainingTable<u32, XorShiftHash, TailInsertion, Transpose>>(
+                ),
+                "Direct Transpose XOR".to_owned(),
+            ),
+        ];
+        generate_scenario_stats("skewed", tables, &SKEWED_LOAD_FACTORS, get_skewed_stats);
+    }
+
+    if MEASURE_ADVERSARIAL_KEYS {
+        let tables: Vec<(Box<dyn HashTableBuilder<u32>>, String)> = vec![
+            (
+                get_builder::<u32, OpenAddressingTable<u32, LinearProber, ModHash>>(),
+                "Linear Mod".to_owned(),
+            ),
+            (
+                get_builder::<u32, DirectChainingTable<u32, ModHash>>(),
+                "Direct Mod".to_owned(),
+            ),
+            (
+                get_builder::<u32, TreeifiedChainingTable<u32, ModHash>>(),
+                "Treeified Mod".to_owned(),
+            ),
+        ];
+        generate_scenario_stats(
+            "adversarial",
+            tables,
+            &ADVERSARIAL_LOAD_FACTORS,
+            get_adversarial_stats,
+        );
+    }
+
+    if MEASURE_LONG_CHAINS {
+        let tables: Vec<(Box<dyn HashTableBuilder<u32>>, String)> = vec![
+            (
+                get_builder::<u32, DirectChainingTable<u32, XorShiftHash>>(),
+                "Direct XOR".to_owned(),
+            ),
+            (
+                get_builder::<u32, SortedChainingTable<u32, XorShiftHash>>(),
+                "Sorted XOR".to_owned(),
+            ),
+            (
+                get_builder::<u32, SeparateChainingTable<u32, XorShiftHash>>(),
+                "Separate XOR".to_owned(),
+            ),
+        ];
+        generate_scenario_stats(
+            "long_chains",
+            tables,
+            &LONG_CHAIN_LOAD_FACTORS,
+            get_long_chain_stats,
+        );
+    }
+
+    if MEASURE_GROWTH {
+        let tables: Vec<(Box<dyn HashTableBuilder<u32>>, String)> = vec![
+            (
+                get_builder::<u32, LinearHashingTable<u32, XorShiftHash>>(),
+                "Linear Hashing XOR".to_owned(),
+            ),
+            (
+                get_builder::<u32, RehashingChainingTable<u32, XorShiftHash>>(),
+                "Rehashing XOR".to_owned(),
+            ),
+            (
+                get_builder::<
+                    u32,
+                    GrowingOpenAddressingTable<u32, LinearProber, XorShiftHash, Incremental>,
+                >(),
+                "Incremental Linear XOR".to_owned(),
+            ),
+            (
+                get_builder::<
+                    u32,
+                    GrowingOpenAddressingTable<u32, LinearProber, XorShiftHash, StopTheWorld>,
+                >(),
+                "Rehashing Linear XOR".to_owned(),
+            ),
+        ];
+        generate_scenario_stats("growth", tables, &GROWTH_LOAD_FACTORS, get_growth_stats);
+    }
+
+    if MEASURE_CONCURRENCY {
+        let tables: Vec<(Box<dyn ConcurrentHashTableBuilder<u32>>, String)> = vec![
+            (
+                get_concurrent_builder::<u32, StripedChainingTable<u32, XorShiftHash, 1>>(),
+                "Locked XOR".to_owned(),
+            ),
+            (
+                get_concurrent_builder::<u32, StripedChainingTable<u32, XorShiftHash>>(),
+                "Striped XOR".to_owned(),
+            ),
+            (
+                get_concurrent_builder::<u32, AtomicOpenAddressingTable<LinearProber, XorShiftHash>>(
+                ),
+                "Atomic Linear XOR".to_owned(),
+            ),
+            (
+                get_concurrent_builder::<
+                    u32,
+                    AtomicOpenAddressingTable<QuadraticProber, XorShiftHash>,
+                >(),
+                "Atomic Quadratic XOR".to_owned(),
+            ),
+        ];
+        measure_concurrency(tables);
+    }
+}
+
+/// Helper function to get a CoalescedTableBuilder for every address factor in ADDRESS_FACTORS
+fn get_address_factor_builders<H: 'static + Hasher<u32>, S: 'static + CoalescingStrategy>(
+) -> SweepBuilders {
+    ADDRESS_FACTORS
+        .iter()
+        .map(|beta| {
+            let builder: Box<dyn HashTableBuilder<u32>> =
+                Box::new(CoalescedTableBuilder::<u32, H, S>::new(*beta));
+            (*beta, builder)
+        })
+        .collect()
 }
 
 /// generates and outputs stats
 ///
 /// generates and outputs stats for every table in `tables`.
 /// Stats are: How many collisions on successful find, how many collisions on
-/// failed find, how much time on successful find, how much time on failed find;
-/// Stats are output to stdout, a hardcoded csv file and 4 graphs (one for every stat)
+/// failed find, how much time on successful find, how much time on failed find,
+/// how many collisions on insertion, how much time on insertion and the
+/// distribution of collisions on successful and failed finds;
+/// Stats are output to stdout, two hardcoded csv files and one graph for every stat
 /// Stats are calculated at every load_factor in LOAD_FACTORS
-/// ITERATIONS_PER_LOAD_FACTOR times
-fn generate_stats(tables: Vec<(Box<dyn HashTableBuilder<u32>>, String)>) {
-    let mut all_stats = Vec::new();
-
-    for (builder, name) in tables {
-        let mut stats = Vec::new();
-        for s in &LOAD_FACTORS {
-            let mut stats_for_this = (0_f32, 0_f64, 0_f32, 0_f64);
-            for _ in 0..ITERATIONS_PER_LOAD_FACTOR {
-                let temp = get_stats(builder.as_ref(), *s);
-                stats_for_this.0 += temp.0;
-                stats_for_this.1 += temp.1;
-                stats_for_this.2 += temp.2;
-                stats_for_this.3 += temp.3;
-            }
-            stats_for_this.0 /= ITERATIONS_PER_LOAD_FACTOR as f32;
-            stats_for_this.1 /= ITERATIONS_PER_LOAD_FACTOR as f64;
-            stats_for_this.2 /= ITERATIONS_PER_LOAD_FACTOR as f32;
-            stats_for_this.3 /= ITERATIONS_PER_LOAD_FACTOR as f64;
-            stats.push(stats_for_this);
-        }
+/// ITERATIONS_PER_LOAD_FACTOR times, see measure_tables.
+/// The seed of every iteration is derived from one random seed
+/// printed at the start, so every measurement can be reproduced.
+/// The memory used by every table is reported after filling. If the
+/// feature `count-allocations` is enabled, the bytes actually allocated
+/// are reported as well. If RESIZE_TO_MAKE_FAIR is set, every table
+/// using more or less memory than it was resized to is reported.
+/// If enabled, histogram and CDF graphs of the distributions are
+/// written for every table
+fn generate_stats(tables: Vec<Registered>) {
+    let seed: u64 = thread_rng().gen();
+    println!("seed: {}", seed);
+    let seeded: Vec<_> = tables
+        .iter()
+        .enumerate()
+        .map(|(t, (builder, name, time))| {
+            // every iteration of every table and load factor gets its own seed
+            let task = t * LOAD_FACTORS.len() * ITERATIONS_PER_LOAD_FACTOR;
+            (
+                builder.as_ref(),
+                name.clone(),
+                *time,
+                seed.wrapping_add(task as u64),
+            )
+        })
+        .collect();
+    let all_results = measure_tables(&seeded, &LOAD_FACTORS);
+    for results in &all_results {
         // print stats for this table
-        print_subtable(&name, &stats, &LOAD_FACTORS);
-        all_stats.push((name, stats));
+        print_subtable(results);
     }
 
-    // create output file for analysis in csv format
-    write_csv(&all_stats, &LOAD_FACTORS);
+    if RESIZE_TO_MAKE_FAIR {
+        print_memory_fairness(&all_results, FAIRNESS_TOLERANCE);
+    }
+
+    // create output files for analysis in csv format
+    write_csv("hashset_data.csv", &all_results);
+    write_distributions_csv(&all_results);
 
     // create graph for every type of HashTable
-    write_graphs(&all_stats, &LOAD_FACTORS, ELEMENT_COUNT);
+    write_graphs("./graphs", &all_results);
+    if WRITE_DISTRIBUTION_GRAPHS {
+        write_distribution_graphs(&all_results);
+    }
 }
 
-/// get stats for one type of hash table
+/// generates and outputs stats of one scenario
 ///
-/// fills the HashTable with `fill` values and then takes measurements
-/// for different statistics
-fn get_stats(builder: &dyn HashTableBuilder<u32>, fill: f64) -> (f32, f64, f32, f64) {
-    let fill = f64::min(fill * ELEMENT_COUNT as f64, ELEMENT_COUNT as f64) as usize;
-    get_stats_rec(builder, fill, 0)
+/// Every table is measured with `stats` at every load factor in `load_factors`.
+/// Every table is measured with the same seeds, so every table gets the
+/// same elements. Stats are output to stdout, "{scenario}_data.csv" and
+/// one graph per stat in the graphs/{scenario} subfolder
+fn generate_scenario_stats(
+    scenario: &str,
+    tables: Vec<(Box<dyn HashTableBuilder<u32>>, String)>,
+    load_factors: &[f64],
+    stats: StatsFn,
+) {
+    let seed: u64 = thread_rng().gen();
+    println!();
+    println!("{} seed: {}", scenario, seed);
+    let mut all_results = Vec::new();
+
+    for (builder, name) in &tables {
+        let results = measure(builder.as_ref(), name, load_factors, seed, stats);
+        print_subtable(&results);
+        all_results.push(results);
+    }
+
+    write_csv(&format!("{}_data.csv", scenario), &all_results);
+    write_graphs(&format!("./graphs/{}", scenario), &all_results);
 }
 
-/// recursively tries to get stats
+/// measures one table at every load factor in `load_factors`
 ///
-/// fills the HashTable with `fill` values and then takes measurements
-/// for different statistics. If it fails at any point it tries again.
-/// One reason for failure could be a nearly full OpenAddressingTable
-/// with QuadraticProbing. At most, 100 attempts are allowed
-fn get_stats_rec(
+/// Every load factor is measured ITERATIONS_PER_LOAD_FACTOR times with
+/// `stats` and the iterations are averaged. Load factor `l` starts with the seed
+/// `seed + l * ITERATIONS_PER_LOAD_FACTOR`, iteration `i` adds `i`
+fn measure(
     builder: &dyn HashTableBuilder<u32>,
-    fill: usize,
-    attempt: usize,
-) -> (f32, f64, f32, f64) {
-    // amount of samples to test at random
-    let random_samples = 1_usize << 16;
+    name: &str,
+    load_factors: &[f64],
+    seed: u64,
+    stats: StatsFn,
+) -> Vec<BenchmarkResult> {
+    let mut results = Vec::new();
+    for (l, s) in load_factors.iter().enumerate() {
+        let seed = seed.wrapping_add((l * ITERATIONS_PER_LOAD_FACTOR) as u64);
+        let iterations: Vec<BenchmarkResult> = (0..ITERATIONS_PER_LOAD_FACTOR)
+            .map(|i| stats(builder, name, *s, seed.wrapping_add(i as u64)))
+            .collect();
+        results.push(
+            BenchmarkResult::average(&iterations)
+                .expect("at least one iteration per load factor is needed"),
+        );
+    }
+    if let Some(e) = results.iter().find_map(|x| x.error) {
+        println!("{} failed: {}", name, e);
+    }
+    results
+}
 
-    let mut table = builder.build();
-    // resize if needed
-    if RESIZE_TO_MAKE_FAIR {
-        table.as_mut().resize_to_bytes(ELEMENT_COUNT << 3, fill);
+/// measures every table at every load factor in `load_factors`
+///
+/// Like measure, but split in two passes. First the
+/// collisions of every table, load factor and iteration are counted, in
+/// parallel if PARALLEL_COLLISIONS is set, as they only depend on the seed.
+/// Then every iteration is timed serially with the seed its collisions were
+/// counted with, so neither timings nor allocated bytes are disturbed by
+/// other threads. Every table is timed with its own timing function.
+/// Table `t` starts with its own seed, seeds of load factors
+/// and iterations are derived from it like in measure
+fn measure_tables(tables: &SeededBuilders, load_factors: &[f64]) -> Vec<Vec<BenchmarkResult>> {
+    let per_table = load_factors.len() * ITERATIONS_PER_LOAD_FACTOR;
+    let task = |index: usize| {
+        let (builder, name, _, seed) = &tables[index / per_table];
+        let l = (index % per_table) / ITERATIONS_PER_LOAD_FACTOR;
+        let i = index % ITERATIONS_PER_LOAD_FACTOR;
+        let seed = seed.wrapping_add((l * ITERATIONS_PER_LOAD_FACTOR + i) as u64);
+        let fill = load_factors[l];
+        let elements = f64::min(fill * ELEMENT_COUNT as f64, ELEMENT_COUNT as f64) as usize;
+        let mut result = get_collision_stats_rec(*builder, elements, seed, 0, RESIZE_TO_MAKE_FAIR);
+        result.table = name.clone();
+        result.load_factor = fill;
+        result
+    };
+    let workers = if PARALLEL_COLLISIONS {
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        1
+    };
+    let mut iterations = run_parallel(tables.len() * per_table, workers, task);
+
+    for (index, result) in iterations.iter_mut().enumerate() {
+        let (builder, _, time, _) = &tables[index / per_table];
+        time(*builder, RESIZE_TO_MAKE_FAIR, result);
     }
+    iterations
+        .chunks(per_table)
+        .zip(tables)
+        .map(|(table, (_, name, _, _))| {
+            // reported once, every iteration of the table may have failed the same way
+            if let Some(e) = table.iter().find_map(|x| x.error) {
+                println!("{} failed: {}", name, e);
+            }
+            table
+                .chunks(ITERATIONS_PER_LOAD_FACTOR)
+                .map(|iterations| {
+                    BenchmarkResult::average(iterations)
+                        .expect("at least one iteration per load factor is needed")
+                })
+                .collect()
+        })
+        .collect()
+}
 
-    // fill hash set with `fill` random values
-    let mut rng = thread_rng();
-    let mut inserted_nums = Vec::with_capacity(fill);
-    for _ in 0..fill {
-        let num = rng.gen();
-        inserted_nums.push(num);
-        if !HashTable::insert(table.as_mut(), &num) {
-            // try again, up to 100 times
-            if attempt > 100 {
-                return (std::f32::NAN, std::f64::NAN, std::f32::NAN, std::f64::NAN);
+/// runs `task` for every index in 0..tasks on `workers` threads
+///
+/// Every thread takes the next index not taken yet, so threads finishing
+/// early take over the remaining tasks. Returns the results in the order
+/// of their indices, independent of the thread that ran them
+fn run_parallel<R: Send>(tasks: usize, workers: usize, task: impl Fn(usize) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers.clamp(1, tasks.max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= tasks {
+                            return done;
+                        }
+                        done.push((index, task(index)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("benchmark worker panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// measures coalesced tables with different address factors
+///
+/// Every sweep holds one builder per address factor. Each of them is
+/// measured at every load factor in ADDRESS_FACTOR_LOAD_FACTORS.
+/// Every address factor is measured with the same seeds, so differences
+/// between them are not caused by different random numbers.
+/// For every sweep and load factor the address factors with the fewest
+/// collisions on success and on failure are printed.
+/// All results are written to "address_factor_data.csv", and one graph
+/// per kind of lookup shows the collisions over the address factor
+fn sweep_address_factors(sweeps: Vec<(SweepBuilders, String)>) {
+    let seed: u64 = thread_rng().gen();
+    println!();
+    println!("address factor sweep seed: {}", seed);
+    let seeded: Vec<_> = sweeps
+        .iter()
+        .flat_map(|(builders, name)| {
+            builders.iter().map(move |(beta, builder)| {
+                let table = format!("{} β={:.2}", name, beta);
+                (builder.as_ref(), table, time_dyn as TimeFn, seed)
+            })
+        })
+        .collect();
+    let all_results = measure_tables(&seeded, &ADDRESS_FACTOR_LOAD_FACTORS);
+    let mut measured = all_results.iter();
+    let mut sweep_results = Vec::new();
+
+    for (builders, name) in &sweeps {
+        let results: Sweep = builders
+            .iter()
+            .zip(&mut measured)
+            .map(|((beta, _), results)| (*beta, results.clone()))
+            .collect();
+        for metric in &[SUCCESS_COLLISIONS, FAILURE_COLLISIONS] {
+            print_sweep_optimum(name, "β", &results, *metric);
+        }
+        sweep_results.push((name.clone(), results));
+    }
+
+    write_csv("address_factor_data.csv", &all_results);
+    for metric in &[SUCCESS_COLLISIONS, FAILURE_COLLISIONS] {
+        write_sweep_graph(
+            "address_factor",
+            "Address factor β",
+            &sweep_results,
+            *metric,
+        );
+    }
+}
+
+/// measures thread-safe tables shared by different numbers of threads
+///
+/// Every table is measured with every number of threads in THREAD_COUNTS
+/// at every load factor in CONCURRENT_LOAD_FACTORS, averaged over
+/// ITERATIONS_PER_LOAD_FACTOR iterations. Every number of threads is
+/// measured with the same seeds. All results are written to
+/// "concurrency_data.csv", and one graph per throughput shows it
+/// over the number of threads
+fn measure_concurrency(tables: Vec<(Box<dyn ConcurrentHashTableBuilder<u32>>, String)>) {
+    let seed: u64 = thread_rng().gen();
+    println!();
+    println!("concurrency seed: {}", seed);
+    let mut all_results = Vec::new();
+    let mut sweep_results = Vec::new();
+
+    for (builder, name) in &tables {
+        let mut sweep = Vec::new();
+        for threads in &THREAD_COUNTS {
+            let table = format!("{} threads={}", name, threads);
+            let mut results = Vec::new();
+            for (l, fill) in CONCURRENT_LOAD_FACTORS.iter().enumerate() {
+                let seed = seed.wrapping_add((l * ITERATIONS_PER_LOAD_FACTOR) as u64);
+                let iterations: Vec<BenchmarkResult> = (0..ITERATIONS_PER_LOAD_FACTOR)
+                    .map(|i| {
+                        get_concurrent_stats(
+                            builder.as_ref(),
+                            &table,
+                            *fill,
+                            *threads,
+                            seed.wrapping_add(i as u64),
+                        )
+                    })
+                    .collect();
+                results.push(
+                    BenchmarkResult::average(&iterations)
+                        .expect("at least one iteration per load factor is needed"),
+                );
             }
-            return get_stats_rec(builder, fill, attempt + 1);
+            print_subtable(&results);
+            all_results.push(results.clone());
+            sweep.push((*threads as f64, results));
         }
+        sweep_results.push((name.clone(), sweep));
+    }
+
+    write_csv("concurrency_data.csv", &all_results);
+    for metric in &[INSERT_THROUGHPUT, LOOKUP_THROUGHPUT] {
+        write_sweep_graph("concurrency", "Threads", &sweep_results, *metric);
+    }
+}
+
+/// get stats of a thread-safe table shared by `threads` threads
+///
+/// The `fill` * ELEMENT_COUNT elements are split evenly between the
+/// threads, which insert them at the same time. Afterwards every thread
+/// does LOOKUPS_PER_THREAD lookups at the same time, half of them of
+/// inserted elements and half of random elements. Every thread counts
+/// its collisions on its own. Throughput is measured from starting the
+/// first thread to joining the last one. If an element can not be
+/// inserted, every metric is NaN
+fn get_concurrent_stats(
+    builder: &dyn ConcurrentHashTableBuilder<u32>,
+    name: &str,
+    fill: f64,
+    threads: usize,
+    seed: u64,
+) -> BenchmarkResult {
+    let elements = f64::min(fill * ELEMENT_COUNT as f64, ELEMENT_COUNT as f64) as usize;
+    let mut result = BenchmarkResult::new(name, fill, elements, seed);
+    for metric in &[
+        INSERT_THROUGHPUT,
+        LOOKUP_THROUGHPUT,
+        INSERT_COLLISIONS,
+        SUCCESS_COLLISIONS,
+        FAILURE_COLLISIONS,
+        MEMORY_USAGE,
+    ] {
+        result.set(*metric, f64::NAN);
     }
-    let mut ns = 0_usize; // number of successful reads
-    let mut nf = 0_usize; // number of failed reads
-    let mut cs = 0_usize; // collisions on successful reads
-    let mut cf = 0_usize; // collisions on failed reads
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let inserted_nums: Vec<u32> = (0..elements).map(|_| rng.gen()).collect();
+    if inserted_nums.is_empty() {
+        return result;
+    }
+    let lookups: Vec<Vec<u32>> = (0..threads)
+        .map(|_| {
+            (0..LOOKUPS_PER_THREAD)
+                .map(|i| {
+                    if i % 2 == 0 {
+                        inserted_nums[rng.gen_range(0, elements)]
+                    } else {
+                        rng.gen()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut table = builder.build();
+    if RESIZE_TO_MAKE_FAIR {
+        result.bytes = Some(ELEMENT_COUNT << 3);
+        if table.resize_to_bytes(ELEMENT_COUNT << 3, elements).is_err() {
+            return result;
+        }
+    }
+    result.buckets = table.buckets();
+    let table = table.as_ref();
+
+    let chunk = elements.div_ceil(threads);
     let start_time = Instant::now();
-    for x in &inserted_nums {
-        table.as_mut().has(x);
+    let inserted: Vec<Option<usize>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = inserted_nums
+            .chunks(chunk)
+            .map(|nums| {
+                scope.spawn(move || {
+                    let mut collisions = 0;
+                    for num in nums {
+                        table.insert(num, &mut collisions).ok()?;
+                    }
+                    Some(collisions)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("inserting thread panicked"))
+            .collect()
+    });
+    let duration_i = start_time.elapsed().as_nanos();
+    let ci: usize = match inserted.into_iter().sum() {
+        Some(ci) => ci,
+        None => return result,
+    };
+
+    let start_time = Instant::now();
+    let counted: Vec<(usize, usize, usize)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = lookups
+            .iter()
+            .map(|lookups| {
+                scope.spawn(move || {
+                    let (mut hits, mut hit_collisions, mut miss_collisions) = (0, 0, 0);
+                    for x in lookups {
+                        let mut collisions = 0;
+                        if table.has(x, &mut collisions) {
+                            hits += 1;
+                            hit_collisions += collisions;
+                        } else {
+                            miss_collisions += collisions;
+                        }
+                    }
+                    (hits, hit_collisions, miss_collisions)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("looking up thread panicked"))
+            .collect()
+    });
+    let duration_l = start_time.elapsed().as_nanos();
+    let hits: usize = counted.iter().map(|c| c.0).sum();
+    let misses = threads * LOOKUPS_PER_THREAD - hits;
+
+    result.set(
+        INSERT_THROUGHPUT,
+        elements as f64 * 1000_f64 / duration_i as f64,
+    );
+    result.set(
+        LOOKUP_THROUGHPUT,
+        (threads * LOOKUPS_PER_THREAD) as f64 * 1000_f64 / duration_l as f64,
+    );
+    result.set(INSERT_COLLISIONS, ci as f64 / elements as f64);
+    result.set(
+        SUCCESS_COLLISIONS,
+        counted.iter().map(|c| c.1).sum::<usize>() as f64 / hits as f64,
+    );
+    result.set(
+        FAILURE_COLLISIONS,
+        counted.iter().map(|c| c.2).sum::<usize>() as f64 / misses as f64,
+    );
+    result.set(MEMORY_USAGE, table.memory_usage() as f64);
+    result
+}
+
+/// get stats of long chains for one type of hash table
+///
+/// like the main measurement, but the table is never resized and filled with
+/// `fill` * ELEMENT_COUNT elements, even if that is more than ELEMENT_COUNT
+fn get_long_chain_stats(
+    builder: &dyn HashTableBuilder<u32>,
+    name: &str,
+    fill: f64,
+    seed: u64,
+) -> BenchmarkResult {
+    let elements = (fill * ELEMENT_COUNT as f64) as usize;
+    let mut result = get_stats_rec(builder, elements, seed, 0, false);
+    result.table = name.to_owned();
+    result.load_factor = fill;
+    result
+}
+
+/// marks every metric of `result` as not measured
+///
+/// Sets the same metrics as a successful measurement, so results
+/// of failed measurements can still be averaged and output
+fn failed(mut result: BenchmarkResult) -> BenchmarkResult {
+    for metric in &[
+        SUCCESS_COLLISIONS,
+        SUCCESS_TIME,
+        FAILURE_COLLISIONS,
+        FAILURE_TIME,
+        INSERT_COLLISIONS,
+        INSERT_TIME,
+        TIMING_OVERHEAD,
+        MEMORY_USAGE,
+    ] {
+        result.set(*metric, f64::NAN);
+    }
+    if allocated_bytes().is_some() {
+        result.set(HEAP_BYTES, f64::NAN);
+    }
+    result.set_distribution_metrics();
+    result
+}
+
+/// builds a table for `fill` elements
+///
+/// If `resize` is set the table is resized to ELEMENT_COUNT * 8 bytes
+/// and the bytes are recorded in `result`. The buckets of the table
+/// are recorded in `result` as well
+fn build(
+    builder: &dyn HashTableBuilder<u32>,
+    fill: usize,
+    resize: bool,
+    result: &mut BenchmarkResult,
+) -> Result<Box<dyn HashTable<u32>>, HashTableError> {
+    let mut table = builder.build();
+    if resize {
+        result.bytes = Some(ELEMENT_COUNT << 3);
+        table.as_mut().resize_to_bytes(ELEMENT_COUNT << 3, fill)?;
     }
-    // duration of `fill` successful reads
-    let duration_s = start_time.elapsed().as_nanos();
+    result.buckets = table.buckets();
+    Ok(table)
+}
+
+/// get stats of skewed lookups for one type of hash table
+///
+/// fills the HashTable with `fill` * ELEMENT_COUNT elements and looks up
+/// SKEWED_LOOKUPS of them, drawn from a Zipf distribution with ZIPF_EXPONENT.
+/// The table is not resized. If an element can not be inserted,
+/// every metric is NaN
+fn get_skewed_stats(
+    builder: &dyn HashTableBuilder<u32>,
+    name: &str,
+    fill: f64,
+    seed: u64,
+) -> BenchmarkResult {
+    let elements = (fill * ELEMENT_COUNT as f64) as usize;
+    let mut result = BenchmarkResult::new(name, fill, elements, seed);
+    result.set(SKEWED_COLLISIONS, f64::NAN);
+    result.set(SKEWED_TIME, f64::NAN);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let inserted_nums: Vec<u32> = (0..elements).map(|_| rng.gen()).collect();
+    if inserted_nums.is_empty() {
+        return result;
+    }
+    // the most popular elements are not the ones inserted first
+    let mut by_popularity = inserted_nums.clone();
+    by_popularity.shuffle(&mut rng);
+    let zipf = Zipf::new(by_popularity.len(), ZIPF_EXPONENT);
+    let lookups: Vec<u32> = (0..SKEWED_LOOKUPS)
+        .map(|_| by_popularity[zipf.sample(&mut rng)])
+        .collect();
+
+    let mut table = match build(builder, elements, false, &mut result) {
+        Ok(table) => table,
+        Err(_) => return result,
+    };
+    for num in &inserted_nums {
+        if table.insert(num).is_err() {
+            return result;
+        }
+        check_invariants(table.as_ref(), "insert", num);
+    }
+
+    table.reset_collisions();
     let start_time = Instant::now();
-    for _ in 0..random_samples {
+    for x in &lookups {
+        table.has(x);
+    }
+    let duration = start_time.elapsed().as_nanos();
+    let collisions = table.get_collisions();
+    // lookups may reorder chains, checking after each of them would distort
+    // the time, so the chains are checked once after all lookups
+    if let Some(x) = lookups.last() {
+        check_invariants(table.as_ref(), "has", x);
+    }
+    result.set(SKEWED_COLLISIONS, collisions as f64 / lookups.len() as f64);
+    result.set(SKEWED_TIME, duration as f64 / lookups.len() as f64);
+    result
+}
+
+/// get stats of single insertions for one type of hash table
+///
+/// inserts `fill` * ELEMENT_COUNT elements into the table and measures
+/// every insertion on its own. Reports the mean, 99th percentile and
+/// maximum time of an insertion, so latency spikes of growing tables
+/// are not averaged away. Afterwards every inserted element and 2^16
+/// random elements are looked up, while a growing table may still be
+/// moving elements. The table is not resized and the number of
+/// buckets is recorded after inserting. If an element can not be
+/// inserted, every metric is NaN
+fn get_growth_stats(
+    builder: &dyn HashTableBuilder<u32>,
+    name: &str,
+    fill: f64,
+    seed: u64,
+) -> BenchmarkResult {
+    let elements = (fill * ELEMENT_COUNT as f64) as usize;
+    let mut result = BenchmarkResult::new(name, fill, elements, seed);
+    for metric in &[
+        INSERT_TIME,
+        INSERT_P99_TIME,
+        INSERT_MAX_TIME,
+        INSERT_COLLISIONS,
+        SUCCESS_COLLISIONS,
+        FAILURE_COLLISIONS,
+        MEMORY_USAGE,
+    ] {
+        result.set(*metric, f64::NAN);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let inserted_nums: Vec<u32> = (0..elements).map(|_| rng.gen()).collect();
+    if inserted_nums.is_empty() {
+        return result;
+    }
+    let mut table = match build(builder, elements, false, &mut result) {
+        Ok(table) => table,
+        Err(_) => return result,
+    };
+    let mut durations = Vec::with_capacity(elements);
+    for num in &inserted_nums {
+        let start_time = Instant::now();
+        let inserted = table.insert(num);
+        durations.push(start_time.elapsed().as_nanos() as f64);
+        if inserted.is_err() {
+            return result;
+        }
+        check_invariants(table.as_ref(), "insert", num);
+    }
+    durations.sort_by(|a, b| a.total_cmp(b));
+    let p99 = durations[((durations.len() - 1) as f64 * 0.99) as usize];
+
+    result.buckets = table.buckets();
+    result.set(
+        INSERT_TIME,
+        durations.iter().sum::<f64>() / durations.len() as f64,
+    );
+    result.set(INSERT_P99_TIME, p99);
+    result.set(INSERT_MAX_TIME, durations[durations.len() - 1]);
+    result.set(
+        INSERT_COLLISIONS,
+        table.get_insert_collisions() as f64 / elements as f64,
+    );
+    result.set(MEMORY_USAGE, table.memory_usage() as f64);
+
+    let (mut hits, mut misses) = (ProbeHistogram::new(), ProbeHistogram::new());
+    for num in &inserted_nums {
+        record_lookup(table.as_ref(), num, &mut hits, &mut misses);
+    }
+    for _ in 0..(1_usize << 16) {
         let num = rng.gen();
-        table.as_mut().has(&num);
+        record_lookup(table.as_ref(), &num, &mut hits, &mut misses);
     }
-    // we assume random numbers nearly always fail
-    // therefore: duration of `random_samples` failed reads
-    let duration_f = start_time.elapsed().as_nanos();
+    result.set(SUCCESS_COLLISIONS, hits.mean());
+    result.set(FAILURE_COLLISIONS, misses.mean());
+    result
+}
 
+/// get stats of adversarial keys for one type of hash table
+///
+/// inserts `fill` * ELEMENT_COUNT distinct multiples of ELEMENT_COUNT into
+/// the table, then looks up every inserted key and as many random multiples
+/// not inserted. The table is not resized. If a key can not be inserted,
+/// every metric is NaN
+fn get_adversarial_stats(
+    builder: &dyn HashTableBuilder<u32>,
+    name: &str,
+    fill: f64,
+    seed: u64,
+) -> BenchmarkResult {
+    // number of distinct multiples of ELEMENT_COUNT in u32
+    let multiples = (u32::MAX as usize / ELEMENT_COUNT) + 1;
+    let elements = ((fill * ELEMENT_COUNT as f64) as usize).min(multiples - 1);
+    let mut result = BenchmarkResult::new(name, fill, elements, seed);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let keys: Vec<u32> = (0..elements).map(|i| (i * ELEMENT_COUNT) as u32).collect();
+    let missing: Vec<u32> = (0..elements)
+        .map(|_| (rng.gen_range(elements, multiples) * ELEMENT_COUNT) as u32)
+        .collect();
+
+    let mut table = match build(builder, elements, false, &mut result) {
+        Ok(table) => table,
+        Err(_) => return failed(result),
+    };
+    let start_time = Instant::now();
+    for key in &keys {
+        if table.insert(key).is_err() {
+            return failed(result);
+        }
+    }
+    let duration_i = start_time.elapsed().as_nanos();
+    // the insertions are timed together, so only the last one is checked
+    if let Some(key) = keys.last() {
+        check_invariants(table.as_ref(), "insert", key);
+    }
+    let ci = table.get_insert_collisions();
+
+    let (mut hits, mut misses) = (ProbeHistogram::new(), ProbeHistogram::new());
+    for key in keys.iter().chain(&missing) {
+        record_lookup(table.as_ref(), key, &mut hits, &mut misses);
+    }
+    result.set(SUCCESS_COLLISIONS, hits.mean());
+    result.set(FAILURE_COLLISIONS, misses.mean());
+    result.set(INSERT_COLLISIONS, ci as f64 / elements as f64);
+    result.set(INSERT_TIME, duration_i as f64 / elements as f64);
+    result.hits = hits;
+    result.misses = misses;
+    result.set_distribution_metrics();
+    result
+}
+
+/// panics if `table` violates one of its invariants after `operation` on `val`
+///
+/// does nothing unless CHECK_INVARIANTS is set
+fn check_invariants(table: &dyn HashTable<u32>, operation: &str, val: &u32) {
+    if !CHECK_INVARIANTS {
+        return;
+    }
+    if let Err(e) = table.check_invariants() {
+        panic!("{} of {} broke the table: {}", operation, val, e);
+    }
+}
+
+/// looks up `val` and records its collisions in `hits` or `misses`
+///
+/// returns true iff `val` was found. Uses `probe_stats`, so neither
+/// the table nor its collision counter is changed
+fn record_lookup(
+    table: &dyn HashTable<u32>,
+    val: &u32,
+    hits: &mut ProbeHistogram,
+    misses: &mut ProbeHistogram,
+) -> bool {
+    let stats = table.probe_stats(val);
+    if stats.found {
+        hits.record(stats.collisions);
+    } else {
+        misses.record(stats.collisions);
+    }
+    stats.found
+}
+
+/// gets stats of one table
+///
+/// counts collisions with get_collision_stats_rec and times the
+/// same elements with time_dyn afterwards
+fn get_stats_rec(
+    builder: &dyn HashTableBuilder<u32>,
+    fill: usize,
+    seed: u64,
+    attempt: usize,
+    resize: bool,
+) -> BenchmarkResult {
+    let mut result = get_collision_stats_rec(builder, fill, seed, attempt, resize);
+    time_dyn(builder, resize, &mut result);
+    result
+}
+
+/// recursively tries to count collisions
+///
+/// fills the HashTable with `fill` values and then counts collisions
+/// of lookups, the time metrics are only set to NaN. If it fails at any
+/// point it tries again with a new seed drawn from the random numbers of
+/// the failed attempt, the seed of the result is the one that succeeded.
+/// One reason for failure could be a nearly full OpenAddressingTable
+/// with QuadraticProbing. At most, 100 attempts are allowed.
+/// If `resize` is set the table is resized first. If the table can
+/// not be resized, the measurement fails immediately with the error in
+/// the result, to be reported once by the caller.
+/// Does not depend on anything but its arguments, so it can run on any thread
+fn get_collision_stats_rec(
+    builder: &dyn HashTableBuilder<u32>,
+    fill: usize,
+    seed: u64,
+    attempt: usize,
+    resize: bool,
+) -> BenchmarkResult {
+    let mut result = BenchmarkResult::new("", 0_f64, fill, seed);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let inserted_nums: Vec<u32> = (0..fill).map(|_| rng.gen()).collect();
+
+    let mut table = match build(builder, fill, resize, &mut result) {
+        Ok(table) => table,
+        Err(e) => {
+            // a smaller budget will not fit for another seed either
+            result.error = Some(e);
+            return failed(result);
+        }
+    };
+
+    // fill hash set with `fill` random values
+    for num in &inserted_nums {
+        if table.insert(num).is_err() {
+            // try again, up to 100 times
+            if attempt > 100 {
+                return failed(result);
+            }
+            return get_collision_stats_rec(builder, fill, rng.gen(), attempt + 1, resize);
+        }
+        check_invariants(table.as_ref(), "insert", num);
+    }
+    let ci = table.get_insert_collisions();
+    let memory = table.memory_usage();
+
+    let (mut hits, mut misses) = (ProbeHistogram::new(), ProbeHistogram::new());
     // First try all numbers we already inserted, so we guarantee
     // some successful reads
     for x in &inserted_nums {
-        HashTable::reset_collisions(table.as_mut());
-        if HashTable::has(table.as_mut(), x) {
-            ns += 1;
-            cs += HashTable::get_collisions(table.as_ref());
-        } else {
+        if !record_lookup(table.as_ref(), x, &mut hits, &mut misses) {
             println!("did not find what we would need to find");
-            nf += 1;
-            cf += HashTable::get_collisions(table.as_ref());
         }
     }
     // Then always try 2^16 more reads with random samples
     for _ in 0..(1_usize << 16) {
         let num = rng.gen();
-        HashTable::reset_collisions(table.as_mut());
-        if HashTable::has(table.as_mut(), &num) {
-            ns += 1;
-            cs += HashTable::get_collisions(table.as_ref());
-        } else {
-            nf += 1;
-            cf += HashTable::get_collisions(table.as_ref());
+        record_lookup(table.as_ref(), &num, &mut hits, &mut misses);
+    }
+    // time metrics are set by time_stats, keep their order in the output
+    result.set(SUCCESS_COLLISIONS, hits.mean());
+    result.set(SUCCESS_TIME, f64::NAN);
+    result.set(FAILURE_COLLISIONS, misses.mean());
+    result.set(FAILURE_TIME, f64::NAN);
+    result.set(INSERT_COLLISIONS, ci as f64 / fill as f64);
+    result.set(INSERT_TIME, f64::NAN);
+    result.set(TIMING_OVERHEAD, f64::NAN);
+    result.set(MEMORY_USAGE, memory as f64);
+    if allocated_bytes().is_some() {
+        result.set(HEAP_BYTES, f64::NAN);
+    }
+    result.hits = hits;
+    result.misses = misses;
+    result.set_distribution_metrics();
+    result
+}
+
+/// times the tables of `builder` through `Box<dyn HashTable>`, see time_stats
+fn time_dyn(builder: &dyn HashTableBuilder<u32>, resize: bool, result: &mut BenchmarkResult) {
+    time_stats(|| builder.build(), resize, result);
+}
+
+/// times tables of type `H` without virtual calls, see time_stats
+///
+/// The builder is only used if STATIC_DISPATCH is not set, it has to
+/// build the default table of type `H`
+fn time_static<H: HashTable<u32> + Default>(
+    builder: &dyn HashTableBuilder<u32>,
+    resize: bool,
+    result: &mut BenchmarkResult,
+) {
+    if STATIC_DISPATCH {
+        time_stats(|| Box::new(H::default()), resize, result);
+    } else {
+        time_dyn(builder, resize, result);
+    }
+}
+
+/// times insertions and lookups of the table measured in `result`
+///
+/// `build` returns a new default table. If `H` is a concrete type,
+/// every operation is statically dispatched.
+///
+/// The table is built again and filled with the elements generated from
+/// the seed of `result`, which get_collision_stats_rec succeeded with.
+/// The bytes allocated by the table are counted while filling it, so no
+/// other thread may allocate meanwhile. Then insertions into new tables,
+/// successful lookups and failed lookups of 2^16 random elements are
+/// timed with a Timer, see `timing`. The overhead of the timing loop is
+/// subtracted and reported. Results of failed measurements are not changed
+fn time_stats<H: HashTable<u32> + ?Sized>(
+    build: impl Fn() -> Box<H>,
+    resize: bool,
+    result: &mut BenchmarkResult,
+) {
+    if result.get(INSERT_COLLISIONS).is_none_or(f64::is_nan) {
+        return;
+    }
+    // amount of samples to test at random
+    let random_samples = 1_usize << 16;
+    let fill = result.elements;
+
+    // generate the values first, so they are not counted as heap bytes of the table
+    let mut rng = StdRng::seed_from_u64(result.seed);
+    let inserted_nums: Vec<u32> = (0..fill).map(|_| rng.gen()).collect();
+    // we assume random numbers nearly always fail
+    let missing_nums: Vec<u32> = (0..random_samples).map(|_| rng.gen()).collect();
+
+    // resizes like build, which already recorded the bytes and buckets
+    let new_table = || {
+        let mut table = build();
+        if resize {
+            table.resize_to_bytes(ELEMENT_COUNT << 3, fill)?;
         }
+        Ok::<_, HashTableError>(table)
+    };
+
+    let heap_before = allocated_bytes();
+    let mut table = match new_table() {
+        Ok(table) => table,
+        Err(_) => {
+            *result = failed(result.clone());
+            return;
+        }
+    };
+    for num in &inserted_nums {
+        if table.insert(num).is_err() {
+            *result = failed(result.clone());
+            return;
+        }
+    }
+    let heap = match (heap_before, allocated_bytes()) {
+        (Some(before), Some(after)) => Some(after - before),
+        _ => None,
+    };
+
+    let clock = if USE_CYCLE_COUNTER {
+        Clock::Cycles
+    } else {
+        Clock::Instant
+    };
+    let timer = Timer::new(clock, WARMUP_ROUNDS, TIMING_SAMPLES);
+    // every round inserts into a new table, as it was resized before
+    let insertion = timer.time(
+        &inserted_nums,
+        || new_table().expect("the table was resized before"),
+        |table, x| table.insert(x).is_ok(),
+    );
+    let success = timer.time(&inserted_nums, || (), |_, x| table.contains(x));
+    let failure = timer.time(&missing_nums, || (), |_, x| table.contains(x));
+
+    result.set(SUCCESS_TIME, success.per_op);
+    result.set(FAILURE_TIME, failure.per_op);
+    result.set(INSERT_TIME, insertion.per_op);
+    result.set(TIMING_OVERHEAD, success.overhead);
+    if let Some(heap) = heap {
+        result.set(HEAP_BYTES, heap as f64);
     }
-    let nf = nf as f32;
-    let cf = cf as f32;
-    let ns = ns as f32;
-    let cs = cs as f32;
-    (
-        (cs / ns),                                   // average number of collisions on success
-        (duration_s as f64 / fill as f64),           // average time on success
-        (cf / nf),                                   // average number of collisions on fail
-        (duration_f as f64 / random_samples as f64), // average time on fail
-    )
 }
@@ -0,0 +1,270 @@
+//! Module for structured benchmark results
+//!
+//! Every measurement of one table at one load factor is collected
+//! in a BenchmarkResult. Writers only iterate over the metrics of a result,
+//! so new metrics can be added without changing any writer.
+use hashset_testing::hashset::{HashTableError, ProbeHistogram};
+
+/// Description of one measured statistic
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Metric {
+    /// short label used for tables printed to stdout
+    pub label: &'static str,
+    /// descriptive name used for csv headers and graph titles
+    pub name: &'static str,
+    /// unit of the statistic, used for csv headers and axis labels
+    pub unit: &'static str,
+    /// file name of the graph, without extension
+    pub file: &'static str,
+}
+
+/// average number of collisions on successful lookups
+pub const SUCCESS_COLLISIONS: Metric = Metric {
+    label: "+ collisions",
+    name: "Collisions on success",
+    unit: "collisions",
+    file: "successful_collisions",
+};
+/// average time of a successful lookup
+pub const SUCCESS_TIME: Metric = Metric {
+    label: "+ time[ns]",
+    name: "Time on success",
+    unit: "ns",
+    file: "successful_time",
+};
+/// 99th percentile of collisions on successful lookups
+pub const SUCCESS_P99_PROBE: Metric = Metric {
+    label: "+ p99 probe",
+    name: "99th percentile of collisions on success",
+    unit: "collisions",
+    file: "successful_p99_probe",
+};
+/// maximum number of collisions on successful lookups
+pub const SUCCESS_MAX_PROBE: Metric = Metric {
+    label: "+ max probe",
+    name: "Maximum collisions on success",
+    unit: "collisions",
+    file: "successful_max_probe",
+};
+/// average number of collisions on failed lookups
+pub const FAILURE_COLLISIONS: Metric = Metric {
+    label: "- collisions",
+    name: "Collisions on failure",
+    unit: "collisions",
+    file: "failure_collisions",
+};
+/// average time of a failed lookup
+pub const FAILURE_TIME: Metric = Metric {
+    label: "- time[ns]",
+    name: "Time on failure",
+    unit: "ns",
+    file: "failure_time",
+};
+/// 99th percentile of collisions on failed lookups
+pub const FAILURE_P99_PROBE: Metric = Metric {
+    label: "- p99 probe",
+    name: "99th percentile of collisions on failure",
+    unit: "collisions",
+    file: "failure_p99_probe",
+};
+/// maximum number of collisions on failed lookups
+pub const FAILURE_MAX_PROBE: Metric = Metric {
+    label: "- max probe",
+    name: "Maximum collisions on failure",
+    unit: "collisions",
+    file: "failure_max_probe",
+};
+/// average number of collisions on insertion
+pub const INSERT_COLLISIONS: Metric = Metric {
+    label: "* collisions",
+    name: "Collisions on insertion",
+    unit: "collisions",
+    file: "insertion_collisions",
+};
+/// average time of an insertion
+pub const INSERT_TIME: Metric = Metric {
+    label: "* time[ns]",
+    name: "Time on insertion",
+    unit: "ns",
+    file: "insertion_time",
+};
+/// 99th percentile of the time of single insertions
+pub const INSERT_P99_TIME: Metric = Metric {
+    label: "* p99 time[ns]",
+    name: "99th percentile of time on insertion",
+    unit: "ns",
+    file: "insertion_p99_time",
+};
+/// maximum time of a single insertion
+pub const INSERT_MAX_TIME: Metric = Metric {
+    label: "* max time[ns]",
+    name: "Maximum time on insertion",
+    unit: "ns",
+    file: "insertion_max_time",
+};
+/// time per operation of an empty loop, subtracted from every time
+pub const TIMING_OVERHEAD: Metric = Metric {
+    label: "overhead[ns]",
+    name: "Timing overhead",
+    unit: "ns",
+    file: "timing_overhead",
+};
+/// bytes used by the table after filling, as calculated by the table
+pub const MEMORY_USAGE: Metric = Metric {
+    label: "memory[B]",
+    name: "Memory used",
+    unit: "bytes",
+    file: "memory_usage",
+};
+/// bytes allocated by the table after filling, as counted by the allocator
+pub const HEAP_BYTES: Metric = Metric {
+    label: "heap[B]",
+    name: "Heap bytes allocated",
+    unit: "bytes",
+    file: "heap_bytes",
+};
+/// average number of collisions on lookups drawn from a Zipf distribution
+pub const SKEWED_COLLISIONS: Metric = Metric {
+    label: "~ collisions",
+    name: "Collisions on skewed lookups",
+    unit: "collisions",
+    file: "skewed_collisions",
+};
+/// average time of a lookup drawn from a Zipf distribution
+pub const SKEWED_TIME: Metric = Metric {
+    label: "~ time[ns]",
+    name: "Time on skewed lookups",
+    unit: "ns",
+    file: "skewed_time",
+};
+/// lookups per microsecond of all threads together
+pub const LOOKUP_THROUGHPUT: Metric = Metric {
+    label: "= lookups/µs",
+    name: "Lookup throughput",
+    unit: "lookups/µs",
+    file: "lookup_throughput",
+};
+/// insertions per microsecond of all threads together
+pub const INSERT_THROUGHPUT: Metric = Metric {
+    label: "= inserts/µs",
+    name: "Insertion throughput",
+    unit: "insertions/µs",
+    file: "insertion_throughput",
+};
+
+/// Results of a table for every value of a swept parameter
+///
+/// Holds the value of the parameter and the results at every load factor
+/// measured. Every value is measured at the same load factors
+pub type Sweep = Vec<(f64, Vec<BenchmarkResult>)>;
+
+/// Results of measuring one table at one load factor
+///
+/// Holds the metadata of the measurement, the probe length distributions
+/// and an ordered list of metrics. Metrics are kept in the order they were
+/// first set, which is the order every writer outputs them in.
+#[derive(Clone, Debug)]
+pub struct BenchmarkResult {
+    /// name of the measured table
+    pub table: String,
+    /// load factor the table was filled to
+    pub load_factor: f64,
+    /// number of elements inserted into the table
+    pub elements: usize,
+    /// number of buckets of the table
+    pub buckets: usize,
+    /// number of bytes the table was resized to, if it was resized
+    pub bytes: Option<usize>,
+    /// seed of the random numbers of the first iteration
+    ///
+    /// Iteration `i` is started with the seed the first iteration was
+    /// started with plus `i`. A failed attempt may be retried with a seed
+    /// drawn from its random numbers, then the seed that succeeded is kept,
+    /// so it differs from the seed the iteration was started with
+    pub seed: u64,
+    /// why the measurement failed, if it failed with an error to report
+    pub error: Option<HashTableError>,
+    /// probe lengths of all successful lookups
+    pub hits: ProbeHistogram,
+    /// probe lengths of all failed lookups
+    pub misses: ProbeHistogram,
+    metrics: Vec<(Metric, f64)>,
+}
+
+impl BenchmarkResult {
+    /// creates a result without any metrics
+    pub fn new(table: &str, load_factor: f64, elements: usize, seed: u64) -> Self {
+        Self {
+            table: table.to_owned(),
+            load_factor,
+            elements,
+            buckets: 0,
+            bytes: None,
+            seed,
+            error: None,
+            hits: ProbeHistogram::new(),
+            misses: ProbeHistogram::new(),
+            metrics: Vec::new(),
+        }
+    }
+
+    /// sets the value of `metric`, appending it if it was not set before
+    pub fn set(&mut self, metric: Metric, value: f64) {
+        match self.metrics.iter_mut().find(|(m, _)| *m == metric) {
+            Some((_, old)) => *old = value,
+            None => self.metrics.push((metric, value)),
+        }
+    }
+
+    /// returns the value of `metric` if it was set
+    pub fn get(&self, metric: Metric) -> Option<f64> {
+        self.metrics
+            .iter()
+            .find(|(m, _)| *m == metric)
+            .map(|(_, value)| *value)
+    }
+
+    /// all metrics in the order they were set
+    pub fn metrics(&self) -> &[(Metric, f64)] {
+        &self.metrics
+    }
+
+    /// sets the metrics derived from the probe length distributions
+    ///
+    /// These are the 99th percentile and the maximum of the collisions
+    /// on successful and on failed lookups. NaN if no lookup was recorded
+    pub fn set_distribution_metrics(&mut self) {
+        let or_nan = |x: Option<usize>| x.map_or(f64::NAN, |x| x as f64);
+        self.set(SUCCESS_P99_PROBE, or_nan(self.hits.percentile(0.99)));
+        self.set(SUCCESS_MAX_PROBE, or_nan(self.hits.max()));
+        self.set(FAILURE_P99_PROBE, or_nan(self.misses.percentile(0.99)));
+        self.set(FAILURE_MAX_PROBE, or_nan(self.misses.max()));
+    }
+
+    /// combines the results of several iterations of the same measurement
+    ///
+    /// Metadata is taken from the first iteration, the error from the first
+    /// failed one. Every metric is averaged
+    /// and the probe length distributions are merged. The metrics derived
+    /// from the distributions are recalculated from the merged distributions,
+    /// if they were set. Returns None if `iterations` is empty
+    pub fn average(iterations: &[BenchmarkResult]) -> Option<BenchmarkResult> {
+        let mut result = iterations.first()?.clone();
+        result.error = iterations.iter().find_map(|x| x.error);
+        for iteration in &iterations[1..] {
+            result.hits.merge(&iteration.hits);
+            result.misses.merge(&iteration.misses);
+        }
+        for (metric, value) in result.metrics.iter_mut() {
+            *value = iterations
+                .iter()
+                .map(|x| x.get(*metric).unwrap_or(f64::NAN))
+                .sum::<f64>()
+                / iterations.len() as f64;
+        }
+        if result.get(SUCCESS_P99_PROBE).is_some() {
+            result.set_distribution_metrics();
+        }
+        Some(result)
+    }
+}
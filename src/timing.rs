@@ -0,0 +1,178 @@
+//! Module for timing operations on tables
+//!
+//! A Timer runs a batch of operations several times: first some warm-up
+//! rounds that are not measured, then a number of measured samples.
+//! Samples far off the others are rejected as outliers, e.g. when the
+//! thread was preempted. The time of an empty loop over the same inputs
+//! is measured the same way and subtracted, so only the operations are timed.
+use std::hint::black_box;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Clock used to measure time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clock {
+    /// the monotonic clock of the operating system
+    Instant,
+    /// the time stamp counter of the processor, read with `rdtsc`
+    ///
+    /// Only available on x86_64, other targets use the monotonic clock.
+    /// Cycles are converted to nanoseconds with a frequency calibrated
+    /// once against the monotonic clock
+    Cycles,
+}
+
+/// Result of timing a batch of operations
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timing {
+    /// nanoseconds per operation, without the overhead
+    pub per_op: f64,
+    /// nanoseconds per operation of the empty loop, subtracted from `per_op`
+    pub overhead: f64,
+    /// number of samples rejected as outliers
+    pub rejected: usize,
+}
+
+/// Times batches of operations with warm-up rounds and repeated samples
+#[derive(Clone, Copy, Debug)]
+pub struct Timer {
+    clock: Clock,
+    warmup: usize,
+    samples: usize,
+}
+
+impl Timer {
+    /// creates a timer running `warmup` unmeasured rounds and `samples` measured rounds
+    ///
+    /// At least one sample is measured. The cycle counter is calibrated
+    /// here, so calibrating it is never part of a measurement
+    pub fn new(clock: Clock, warmup: usize, samples: usize) -> Self {
+        if clock == Clock::Cycles {
+            nanos_per_cycle();
+        }
+        Self {
+            clock,
+            warmup,
+            samples: samples.max(1),
+        }
+    }
+
+    /// times `op` on every input
+    ///
+    /// Every round calls `setup` first, untimed, and passes its result
+    /// to every call of `op` in this round. Inputs and results are passed
+    /// through `black_box`, so the optimizer can not remove the operations.
+    /// Returns the mean time of one operation over all samples that are
+    /// not outliers, minus the overhead of the loop itself.
+    /// All times are NaN if there are no inputs
+    pub fn time<S, I, R>(
+        &self,
+        inputs: &[I],
+        mut setup: impl FnMut() -> S,
+        mut op: impl FnMut(&mut S, &I) -> R,
+    ) -> Timing {
+        let mut measured = Vec::with_capacity(self.samples);
+        let mut empty = Vec::with_capacity(self.samples);
+        for round in 0..self.warmup + self.samples {
+            let mut state = setup();
+            let start = self.now();
+            for input in inputs {
+                black_box(op(&mut state, black_box(input)));
+            }
+            let duration = self.elapsed(start);
+
+            let start = self.now();
+            for input in inputs {
+                black_box(input);
+            }
+            let overhead = self.elapsed(start);
+            if round >= self.warmup {
+                measured.push(duration / inputs.len() as f64);
+                empty.push(overhead / inputs.len() as f64);
+            }
+        }
+        let rejected = reject_outliers(&mut measured);
+        reject_outliers(&mut empty);
+        let overhead = mean(&empty);
+        Timing {
+            per_op: (mean(&measured) - overhead).max(0_f64),
+            overhead,
+            rejected,
+        }
+    }
+
+    /// reads the clock
+    fn now(&self) -> u64 {
+        match self.clock {
+            Clock::Instant => epoch().elapsed().as_nanos() as u64,
+            Clock::Cycles => cycles(),
+        }
+    }
+
+    /// returns the nanoseconds since `start` was read from the clock
+    fn elapsed(&self, start: u64) -> f64 {
+        let ticks = self.now().saturating_sub(start) as f64;
+        match self.clock {
+            Clock::Instant => ticks,
+            Clock::Cycles => ticks * nanos_per_cycle(),
+        }
+    }
+}
+
+/// removes every sample outside of Tukey's fences and returns how many were removed
+///
+/// The fences are 1.5 interquartile ranges below the first and above
+/// the third quartile. The remaining samples are sorted
+fn reject_outliers(samples: &mut Vec<f64>) -> usize {
+    samples.sort_by(|a, b| a.total_cmp(b));
+    if samples.len() < 4 {
+        return 0;
+    }
+    let quartile = |q: f64| samples[((samples.len() - 1) as f64 * q).round() as usize];
+    let (q1, q3) = (quartile(0.25), quartile(0.75));
+    let fence = 1.5 * (q3 - q1);
+    let before = samples.len();
+    samples.retain(|x| *x >= q1 - fence && *x <= q3 + fence);
+    before - samples.len()
+}
+
+/// mean of `samples`, NaN if there are none
+fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+/// instant all readings of the monotonic clock are relative to
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// reads the time stamp counter
+#[cfg(target_arch = "x86_64")]
+fn cycles() -> u64 {
+    // SAFETY: rdtsc is available on every x86_64 processor
+    unsafe { std::arch::x86_64::_rdtsc() }
+}
+
+/// falls back to the monotonic clock in nanoseconds
+#[cfg(not(target_arch = "x86_64"))]
+fn cycles() -> u64 {
+    epoch().elapsed().as_nanos() as u64
+}
+
+/// nanoseconds per cycle of the time stamp counter
+///
+/// Calibrated once by counting the cycles of 20ms of the monotonic clock
+fn nanos_per_cycle() -> f64 {
+    static NANOS_PER_CYCLE: OnceLock<f64> = OnceLock::new();
+    *NANOS_PER_CYCLE.get_or_init(|| {
+        if cfg!(not(target_arch = "x86_64")) {
+            return 1_f64;
+        }
+        let start = Instant::now();
+        let start_cycles = cycles();
+        while start.elapsed().as_millis() < 20 {}
+        let nanos = start.elapsed().as_nanos() as f64;
+        nanos / cycles().saturating_sub(start_cycles).max(1) as f64
+    })
+}
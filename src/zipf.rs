@@ -0,0 +1,42 @@
+//! Module for sampling skewed access patterns
+//!
+//! Contains a sampler for the Zipf distribution, used to look up
+//! some elements far more often than others.
+use rand::Rng;
+
+/// Sampler for the Zipf distribution over the ranks 0..n
+///
+/// Rank k is drawn with a probability proportional to 1/(k+1)^s.
+/// The cumulative distribution is calculated once, every sample
+/// is found with binary search on it
+pub struct Zipf {
+    cdf: Vec<f64>,
+}
+
+impl Zipf {
+    /// creates a sampler for `n` ranks with the exponent `s`
+    ///
+    /// s = 0 is the uniform distribution, larger exponents are more skewed
+    pub fn new(n: usize, s: f64) -> Self {
+        let mut cdf = Vec::with_capacity(n);
+        let mut sum = 0_f64;
+        for k in 0..n {
+            sum += 1_f64 / ((k + 1) as f64).powf(s);
+            cdf.push(sum);
+        }
+        for x in cdf.iter_mut() {
+            *x /= sum;
+        }
+        Self { cdf }
+    }
+
+    /// draws a rank, panics if there are no ranks
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        assert!(!self.cdf.is_empty(), "cannot sample from zero ranks");
+        let u: f64 = rng.gen();
+        // first rank whose cumulative probability exceeds u
+        self.cdf
+            .partition_point(|x| *x <= u)
+            .min(self.cdf.len() - 1)
+    }
+}